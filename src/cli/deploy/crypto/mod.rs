@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests;
+
+use std::io::{BufReader, Read, Write};
+use std::str::FromStr;
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::x25519;
+use age::{Decryptor, Encryptor, IdentityFile, IdentityFileEntry};
+use clap::ArgMatches;
+use failure::{Error, ResultExt};
+
+use crate::cli::deploy::input;
+
+/// Environment variable holding the age identities themselves, for CI systems that expose secrets as variables
+pub const IDENTITY_ENV: &str = "DEPLOYMENT_AGE_IDENTITY";
+
+const BINARY_HEADER: &[u8] = b"age-encryption.org/v1\n";
+const ARMOR_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// X25519 identities used to decrypt age encrypted variables files
+pub struct Identities {
+    identities: Vec<x25519::Identity>,
+}
+
+impl Identities {
+    /// Parses identities in the age identity file format, one `AGE-SECRET-KEY-1...` per line with `#` comments
+    pub fn parse(content: &str, source: &str) -> Result<Identities, Error> {
+        let identities: Vec<x25519::Identity> = IdentityFile::from_buffer(BufReader::new(content.as_bytes()))
+            .context(format!("Failed to parse age identities from {}", source))?
+            .into_identities()
+            .into_iter()
+            .map(|IdentityFileEntry::Native(identity)| identity)
+            .collect();
+        if identities.is_empty() {
+            return Err(format_err!("Found no age identities in {}", source));
+        }
+        Ok(Identities { identities })
+    }
+
+    /// Identities from the --age-identity file, or from the DEPLOYMENT_AGE_IDENTITY environment variable
+    pub fn from_args(subcommand: &ArgMatches) -> Result<Option<Identities>, Error> {
+        if let Some(path) = subcommand.value_of("age-identity") {
+            let content = input::read_to_string(path)
+                .context(format!("Unable to read age identity file {}", path))?;
+            return Identities::parse(content.as_str(), path).map(Some);
+        }
+        match std::env::var(IDENTITY_ENV) {
+            Ok(content) => Identities::parse(content.as_str(), IDENTITY_ENV).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn recipients(&self) -> Vec<x25519::Recipient> {
+        self.identities.iter().map(x25519::Identity::to_public).collect()
+    }
+}
+
+/// Identities from the arguments, read when the first encrypted file needs them, so a broken identity does not affect
+/// deploys without encrypted files
+#[derive(Default)]
+pub struct LazyIdentities<'a> {
+    subcommand: Option<&'a ArgMatches<'a>>,
+    identities: Option<Option<Identities>>,
+}
+
+impl<'a> LazyIdentities<'a> {
+    pub fn new(subcommand: &'a ArgMatches<'a>) -> LazyIdentities<'a> {
+        LazyIdentities { subcommand: Some(subcommand), identities: None }
+    }
+
+    pub fn get(&mut self) -> Result<Option<&Identities>, Error> {
+        if self.identities.is_none() {
+            self.identities = Some(match self.subcommand {
+                Some(subcommand) => Identities::from_args(subcommand)?,
+                None => None,
+            });
+        }
+        Ok(self.identities.as_ref().and_then(Option::as_ref))
+    }
+}
+
+pub fn parse_recipient(recipient: &str) -> Result<x25519::Recipient, Error> {
+    x25519::Recipient::from_str(recipient)
+        .map_err(|e| format_err!("Invalid age recipient {}: {}", recipient, e))
+}
+
+/// Whether the content is age encrypted, either binary or ASCII armored
+pub fn is_encrypted(content: &[u8]) -> bool {
+    content.starts_with(BINARY_HEADER) || content.starts_with(ARMOR_HEADER)
+}
+
+/// Decrypts binary or ASCII armored age content in memory
+pub fn decrypt(content: &[u8], identities: &Identities) -> Result<Vec<u8>, Error> {
+    let decryptor = match Decryptor::new(ArmoredReader::new(content)).map_err(|e| format_err!("{}", e))? {
+        Decryptor::Recipients(decryptor) => decryptor,
+        _ => return Err(format_err!("Passphrase encrypted files are not supported, encrypt to an X25519 recipient")),
+    };
+    let mut reader = decryptor
+        .decrypt(identities.identities.iter().map(|identity| identity as &dyn age::Identity))
+        .map_err(|e| format_err!("{}", e))?;
+    let mut plaintext = vec![];
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+/// Encrypts to the recipients, ASCII armored so the result diffs and reviews like any other text file
+pub fn encrypt(plaintext: &[u8], recipients: &[x25519::Recipient]) -> Result<Vec<u8>, Error> {
+    let recipients: Vec<Box<dyn age::Recipient + Send>> = recipients.iter()
+        .map(|recipient| Box::new(recipient.clone()) as Box<dyn age::Recipient + Send>)
+        .collect();
+    let encryptor = Encryptor::with_recipients(recipients)
+        .ok_or_else(|| format_err!("At least one age recipient is needed to encrypt"))?;
+
+    let mut encrypted = vec![];
+    let armored = ArmoredWriter::wrap_output(&mut encrypted, Format::AsciiArmor)?;
+    let mut writer = encryptor.wrap_output(armored).map_err(|e| format_err!("{}", e))?;
+    writer.write_all(plaintext)?;
+    writer.finish()?.finish()?;
+    Ok(encrypted)
+}
@@ -0,0 +1,37 @@
+use age::secrecy::ExposeSecret;
+use age::x25519;
+
+use super::{decrypt, encrypt, is_encrypted, Identities};
+
+const IDENTITY: &str = include_str!("../../../../testdata/age_identity.txt");
+const ENCRYPTED_VARS: &[u8] = include_bytes!("../../../../testdata/vars_encrypted.yaml.age");
+
+#[test]
+fn test_decrypt_vars_file() {
+    let identities = assert_ok!(Identities::parse(IDENTITY, "age_identity.txt"));
+
+    assert!(is_encrypted(ENCRYPTED_VARS));
+    assert_eq!(b"env:\n  TEST_KEY: encrypted value\n".to_vec(), assert_ok!(decrypt(ENCRYPTED_VARS, &identities)));
+}
+
+#[test]
+fn test_encrypt_roundtrip() {
+    let identities = assert_ok!(Identities::parse(IDENTITY, "age_identity.txt"));
+    let encrypted = assert_ok!(encrypt(b"version: 1.0.0\n", &identities.recipients()));
+
+    assert!(is_encrypted(encrypted.as_slice()));
+    assert!(!is_encrypted(b"version: 1.0.0\n"));
+    assert_eq!(b"version: 1.0.0\n".to_vec(), assert_ok!(decrypt(encrypted.as_slice(), &identities)));
+}
+
+#[test]
+fn test_decrypt_with_other_identity() {
+    let other = assert_ok!(Identities::parse(x25519::Identity::generate().to_string().expose_secret(), "generated"));
+    assert!(decrypt(ENCRYPTED_VARS, &other).is_err());
+}
+
+#[test]
+fn test_parse_identities_without_keys() {
+    let error = Identities::parse("# no keys here\n", "age_identity.txt").err().unwrap();
+    assert_eq!("Found no age identities in age_identity.txt", error.to_string());
+}
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use failure::Error;
+
+/// An error at a position in a template or resource file, displayed with a snippet of the offending line
+#[derive(Fail, Debug)]
+pub struct SourceError {
+    pub file_name: String,
+    pub message: String,
+    /// 1-based line and column in the file
+    pub line: usize,
+    pub column: usize,
+    pub source_lines: Vec<(usize, String)>,
+    /// The rendered line and 1-based line number, if templating changed the line the error points to
+    pub rendered_line: Option<(usize, String)>,
+}
+
+impl SourceError {
+    pub fn new(file_name: &str, message: String, source: &str, line: usize, column: usize) -> SourceError {
+        SourceError {
+            file_name: file_name.to_owned(),
+            message,
+            line,
+            column,
+            source_lines: snippet_lines(source, line),
+            rendered_line: None,
+        }
+    }
+
+    /// Creates an error from a position in the rendered output of a template, pointing at the template line it
+    /// most likely came from
+    pub fn from_rendered(file_name: &str, message: String, template: &str, rendered: &str, rendered_line: usize, column: usize) -> SourceError {
+        let line = map_rendered_line(template, rendered, rendered_line);
+        let rendered_text = rendered.lines().nth(rendered_line.saturating_sub(1)).unwrap_or("");
+        let mut error = SourceError::new(file_name, message, template, line, column);
+        if template.lines().nth(line - 1) != Some(rendered_text) {
+            error.rendered_line = Some((rendered_line, rendered_text.to_owned()));
+        }
+        error
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.message)?;
+        let width = self.source_lines.iter()
+            .map(|(number, _)| number.to_string().len())
+            .max()
+            .unwrap_or(1);
+        match self.rendered_line {
+            Some((rendered_number, _)) => writeln!(f, "{:>width$}--> {}:{} (rendered line {}, col {})", "", self.file_name, self.line, rendered_number, self.column, width = width)?,
+            None => writeln!(f, "{:>width$}--> {}:{}:{}", "", self.file_name, self.line, self.column, width = width)?,
+        }
+        writeln!(f, "{:>width$} |", "", width = width)?;
+        for (number, text) in &self.source_lines {
+            writeln!(f, "{:>width$} | {}", number, text, width = width)?;
+        }
+        match &self.rendered_line {
+            Some((_, rendered)) => {
+                writeln!(f, "{:>width$} = rendered as:", "", width = width)?;
+                writeln!(f, "{:>width$} | {}", "", rendered, width = width)?;
+                write!(f, "{:>width$} | {}", "", caret(rendered, self.column), width = width)
+            },
+            None => {
+                let text = self.source_lines.last().map(|(_, text)| text.as_str()).unwrap_or("");
+                write!(f, "{:>width$} | {}", "", caret(text, self.column), width = width)
+            },
+        }
+    }
+}
+
+/// The line with the error and the line before it, for context
+fn snippet_lines(source: &str, line: usize) -> Vec<(usize, String)> {
+    source.lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.to_owned()))
+        .skip(line.saturating_sub(2))
+        .take(if line > 1 { 2 } else { 1 })
+        .collect()
+}
+
+/// Spaces up to the column, keeping tabs so the caret lines up with the text above it
+fn caret(text: &str, column: usize) -> String {
+    let mut caret: String = text.chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    caret.push('^');
+    caret
+}
+
+/// Maps a 1-based line in the rendered output back to the template line it most likely came from. Lines that are
+/// unchanged by templating are matched up as anchors using the longest common subsequence, lines in between are
+/// counted from the closest anchor above
+pub fn map_rendered_line(template: &str, rendered: &str, rendered_line: usize) -> usize {
+    let template_lines: Vec<&str> = template.lines().collect();
+    let rendered_lines: Vec<&str> = rendered.lines().collect();
+    let anchors = common_lines(&template_lines, &rendered_lines);
+    let target = rendered_line.saturating_sub(1);
+
+    let (previous_rendered, previous_template) = anchors.iter()
+        .take_while(|(r, _)| *r <= target)
+        .last()
+        .map(|(r, t)| (*r as isize, *t as isize))
+        .unwrap_or((-1, -1));
+    if previous_rendered == target as isize {
+        return previous_template as usize + 1;
+    }
+    let next_template = anchors.iter()
+        .find(|(r, _)| *r > target)
+        .map(|(_, t)| *t as isize)
+        .unwrap_or(template_lines.len() as isize);
+
+    let candidate = previous_template + (target as isize - previous_rendered);
+    let line = if next_template - 1 > previous_template {
+        candidate.min(next_template - 1)
+    } else {
+        previous_template
+    };
+    line.max(0) as usize + 1
+}
+
+/// Pairs of 0-based (rendered, template) indices of identical lines, in order
+pub fn common_lines(template: &[&str], rendered: &[&str]) -> Vec<(usize, usize)> {
+    let (n, m) = (template.len(), rendered.len());
+    let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+    for t in (0..n).rev() {
+        for r in (0..m).rev() {
+            lengths[t][r] = if template[t] == rendered[r] {
+                lengths[t + 1][r + 1] + 1
+            } else {
+                lengths[t + 1][r].max(lengths[t][r + 1])
+            };
+        }
+    }
+
+    let mut pairs = vec![];
+    let (mut t, mut r) = (0, 0);
+    while t < n && r < m {
+        if template[t] == rendered[r] {
+            pairs.push((r, t));
+            t += 1;
+            r += 1;
+        } else if lengths[t + 1][r] >= lengths[t][r + 1] {
+            t += 1;
+        } else {
+            r += 1;
+        }
+    }
+    pairs
+}
+
+/// The 1-based line and column of a YAML or JSON parse error somewhere in the chain of causes
+pub fn parse_error_location(error: &Error) -> Option<(usize, usize)> {
+    error.iter_chain().find_map(|cause| {
+        if let Some(yaml_error) = cause.downcast_ref::<serde_yaml::Error>() {
+            // Errors from a multi document stream are shared between the documents and do not expose their
+            // location, but still include it in the message
+            yaml_error.location()
+                .map(|location| (location.line(), location.column()))
+                .or_else(|| split_yaml_location(yaml_error.to_string().as_str()).1)
+                .filter(|(line, _)| *line > 0)
+        } else if let Some(json_error) = cause.downcast_ref::<serde_json::Error>() {
+            Some((json_error.line(), json_error.column())).filter(|(line, _)| *line > 0)
+        } else {
+            None
+        }
+    })
+}
+
+/// Adds the file position and a snippet to a parse error of a resource file, when the parser reported one. For
+/// templated files the position in the rendered output is mapped back to the template
+pub fn locate_parse_error(error: Error, file_name: &str, template: Option<&str>, content: &str) -> Error {
+    let (line, column) = match parse_error_location(&error) {
+        Some(location) => location,
+        None => return error,
+    };
+    let message = error.iter_chain()
+        .map(|cause| split_yaml_location(cause.to_string().as_str()).0.to_owned())
+        .collect::<Vec<String>>()
+        .join(": ");
+    match template {
+        Some(template) => SourceError::from_rendered(file_name, message, template, content, line, column).into(),
+        None => SourceError::new(file_name, message, content, line, column).into(),
+    }
+}
+
+/// Splits a trailing ` at line <line> column <column>` from a serde_yaml error message
+fn split_yaml_location(message: &str) -> (&str, Option<(usize, usize)>) {
+    let location = message.rfind(" at line ").and_then(|index| {
+        let mut parts = message[index + " at line ".len()..].splitn(2, " column ");
+        let line = parts.next()?.parse().ok()?;
+        let column = parts.next()?.parse().ok()?;
+        Some((index, (line, column)))
+    });
+    match location {
+        Some((index, location)) => (&message[..index], Some(location)),
+        None => (message, None),
+    }
+}
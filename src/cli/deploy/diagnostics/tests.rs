@@ -0,0 +1,42 @@
+use failure::ResultExt;
+
+use super::{locate_parse_error, map_rendered_line, SourceError};
+
+const TEMPLATE: &str = "kind: ConfigMap\nmetadata:\n  labels:\n{{#each labels}}\n    {{@key}}: {{this}}\n{{/each}}\ndata:\n  key: {{value}}\n";
+const RENDERED: &str = "kind: ConfigMap\nmetadata:\n  labels:\n\n    a: 1\n\n    b: 2\n\ndata:\n  key: foo: bar\n";
+
+#[test]
+fn test_map_rendered_line() {
+    assert_eq!(1, map_rendered_line(TEMPLATE, RENDERED, 1));
+    assert_eq!(3, map_rendered_line(TEMPLATE, RENDERED, 3));
+    assert_eq!(5, map_rendered_line(TEMPLATE, RENDERED, 5));
+    // Repeated output is clamped to the last template line before the next unchanged line
+    assert_eq!(6, map_rendered_line(TEMPLATE, RENDERED, 7));
+    assert_eq!(7, map_rendered_line(TEMPLATE, RENDERED, 9));
+    assert_eq!(8, map_rendered_line(TEMPLATE, RENDERED, 10));
+}
+
+#[test]
+fn test_source_error_display() {
+    let error = SourceError::new("nais.yaml", "Failed to parse yaml in nais.yaml".to_owned(), "kind: A\nmetadata:\n  name: [a\n", 3, 9);
+    assert_eq!("Failed to parse yaml in nais.yaml\n --> nais.yaml:3:9\n  |\n2 | metadata:\n3 |   name: [a\n  |         ^", error.to_string());
+}
+
+#[test]
+fn test_locate_rendered_yaml_error() {
+    let parse_error = serde_yaml::from_str::<serde_yaml::Value>(RENDERED)
+        .context("Failed to parse yaml in nais.yaml")
+        .unwrap_err();
+
+    let error = locate_parse_error(parse_error.into(), "nais.yaml", Some(TEMPLATE), RENDERED);
+    let source_error = error.downcast_ref::<SourceError>().unwrap();
+    assert_eq!(8, source_error.line);
+    assert_eq!(Some((10, "  key: foo: bar".to_owned())), source_error.rendered_line);
+    assert_eq!("Failed to parse yaml in nais.yaml: mapping values are not allowed in this context", source_error.message);
+}
+
+#[test]
+fn test_line_zero_from_parser() {
+    let error = SourceError::from_rendered("configmap.yaml", "invalid".to_owned(), TEMPLATE, RENDERED, 0, 1);
+    assert_eq!(1, error.line);
+}
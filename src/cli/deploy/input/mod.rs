@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::io::Read;
+
+use clap::ArgMatches;
+use failure::{Error, ResultExt};
+
+/// Path used on the command line to read from stdin instead of a file
+pub const STDIN: &str = "-";
+
+/// Reads the whole file, or stdin if the path is `-`
+pub fn read_to_string(path: &str) -> Result<String, Error> {
+    let mut content = String::new();
+    if path == STDIN {
+        std::io::stdin().read_to_string(&mut content)
+            .context("Failed to read from stdin")?;
+    } else {
+        File::open(path)
+            .context(format!("Unable to open file {}", path))?
+            .read_to_string(&mut content)
+            .context(format!("Failed to read file {}", path))?;
+    }
+    Ok(content)
+}
+
+/// Reads the whole file as bytes, or stdin if the path is `-`
+pub fn read_bytes(path: &str) -> Result<Vec<u8>, Error> {
+    let mut content = vec![];
+    if path == STDIN {
+        std::io::stdin().read_to_end(&mut content)
+            .context("Failed to read from stdin")?;
+    } else {
+        File::open(path)
+            .context(format!("Unable to open file {}", path))?
+            .read_to_end(&mut content)
+            .context(format!("Failed to read file {}", path))?;
+    }
+    Ok(content)
+}
+
+/// Arguments of the deploy commands that can read from stdin, with the flag used in errors
+pub const DEPLOY_STDIN_ARGUMENTS: &[(&str, &str)] = &[("resource", "--resource"), ("raw-resource", "--raw-resource"), ("variables", "--vars"), ("age-identity", "--age-identity")];
+
+/// Arguments of the vars encrypt and decrypt commands that can read from stdin
+pub const VARS_STDIN_ARGUMENTS: &[(&str, &str)] = &[("file", "<file>"), ("age-identity", "--age-identity")];
+
+/// Stdin can only be read once, so at most one of the arguments can be `-`
+pub fn check_stdin_arguments(subcommand: &ArgMatches, arguments: &[(&str, &str)]) -> Result<(), Error> {
+    let stdin_arguments: Vec<&str> = arguments.iter()
+        .flat_map(|(name, flag)| subcommand.values_of(name).into_iter().flatten().map(move |value| (*flag, value)))
+        .filter(|(_, value)| *value == STDIN)
+        .map(|(flag, _)| flag)
+        .collect();
+    if stdin_arguments.len() > 1 {
+        let flags: Vec<&str> = arguments.iter().map(|(_, flag)| *flag).collect();
+        let (last, rest) = flags.split_last().unwrap();
+        return Err(format_err!("Only one of {} and {} can read from stdin, got - for {}",
+                               rest.join(", "), last, stdin_arguments.join(", ")));
+    }
+    Ok(())
+}
@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::str::FromStr;
+
+use clap::ArgMatches;
+use failure::Error;
+use serde_json::Value;
+
+pub const LINT_LEVELS: &[&str] = &["off", "warn", "error"];
+pub const LINT_RULES: &[&str] = &["team-label", "team-namespace", "image-tag", "probes"];
+
+/// Kinds with a pod template in `spec.template`, whose containers are linted like the Application spec
+const WORKLOAD_KINDS: &[&str] = &["Deployment", "StatefulSet", "DaemonSet", "Job"];
+/// Kinds that do not live in a namespace, skipped by the team-namespace rule
+const CLUSTER_SCOPED_KINDS: &[&str] = &["Namespace", "ClusterRole", "ClusterRoleBinding", "CustomResourceDefinition",
+    "PersistentVolume", "StorageClass", "PriorityClass", "ValidatingWebhookConfiguration", "MutatingWebhookConfiguration"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Off,
+    Warn,
+    Error,
+}
+
+impl FromStr for LintLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<LintLevel, Error> {
+        match s {
+            "off" => Ok(LintLevel::Off),
+            "warn" => Ok(LintLevel::Warn),
+            "error" => Ok(LintLevel::Error),
+            _ => Err(format_err!("Unknown lint level {}, expected one of {}", s, LINT_LEVELS.join(", "))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// `metadata.labels.team` is set to another team than --team
+    TeamLabel,
+    /// `metadata.namespace` is not the namespace of the team on a GCP cluster
+    TeamNamespace,
+    /// An image without a tag or tagged `latest`
+    ImageTag,
+    /// No liveness or readiness probe
+    Probes,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::TeamLabel => "team-label",
+            Rule::TeamNamespace => "team-namespace",
+            Rule::ImageTag => "image-tag",
+            Rule::Probes => "probes",
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Rule, Error> {
+        match s {
+            "team-label" => Ok(Rule::TeamLabel),
+            "team-namespace" => Ok(Rule::TeamNamespace),
+            "image-tag" => Ok(Rule::ImageTag),
+            "probes" => Ok(Rule::Probes),
+            _ => Err(format_err!("Unknown lint rule {}, expected one of {}", s, LINT_RULES.join(", "))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: Rule,
+    /// The `<kind>/<name>` of the resource
+    pub resource: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.rule.name(), self.resource, self.message)
+    }
+}
+
+#[derive(Fail, Debug)]
+pub struct LintErrors {
+    pub findings: Vec<Finding>,
+}
+
+impl fmt::Display for LintErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Resources break the lint rules (use --lint-level=warn to deploy anyway or --disable-lint=<rule> to turn a rule off):")?;
+        for finding in &self.findings {
+            write!(f, "\n  {}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+/// The team and cluster the resources are deployed for, and the rules to check them with
+pub struct Linter<'a> {
+    pub team: &'a str,
+    pub cluster: &'a str,
+    pub disabled: Vec<Rule>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn from_args(subcommand: &'a ArgMatches) -> Result<Linter<'a>, Error> {
+        Ok(Linter {
+            team: subcommand.value_of("team").unwrap(),
+            cluster: subcommand.value_of("cluster").unwrap(),
+            disabled: subcommand.values_of("disable-lint").into_iter()
+                .flatten()
+                .map(str::parse)
+                .collect::<Result<Vec<Rule>, Error>>()?,
+        })
+    }
+
+    /// Every finding of the enabled rules, in the order of the resources
+    pub fn lint(&self, resources: &[Value]) -> Vec<Finding> {
+        let mut findings = vec![];
+        for resource in resources {
+            let kind = resource.get("kind").and_then(Value::as_str).unwrap_or("");
+            let name = resource.pointer("/metadata/name").and_then(Value::as_str).unwrap_or("<unnamed>");
+            let mut finding = |rule: Rule, message: String| if !self.disabled.contains(&rule) {
+                findings.push(Finding { rule, resource: format!("{}/{}", kind, name), message });
+            };
+
+            if let Some(label) = resource.pointer("/metadata/labels/team").and_then(Value::as_str) {
+                if label != self.team {
+                    finding(Rule::TeamLabel, format!("metadata.labels.team is {}, but the deployment is for the team {}", label, self.team));
+                }
+            }
+            if self.cluster.ends_with("-gcp") && !CLUSTER_SCOPED_KINDS.contains(&kind) {
+                match resource.pointer("/metadata/namespace").and_then(Value::as_str) {
+                    Some(namespace) if namespace == self.team => {},
+                    Some(namespace) => finding(Rule::TeamNamespace, format!("metadata.namespace is {}, but resources on {} belong in the team namespace {}", namespace, self.cluster, self.team)),
+                    None => finding(Rule::TeamNamespace, format!("metadata.namespace is not set, resources on {} belong in the team namespace {}", self.cluster, self.team)),
+                }
+            }
+
+            for container in containers(resource) {
+                match container.image {
+                    Some(image) => match image_tag(image) {
+                        Some("latest") => finding(Rule::ImageTag, format!("{} uses the latest tag of {}, deploy a fixed version instead", container.description, image)),
+                        None => finding(Rule::ImageTag, format!("{} uses {} without a tag, deploy a fixed version instead", container.description, image)),
+                        Some(_) => {},
+                    },
+                    None => finding(Rule::ImageTag, format!("{} has no image", container.description)),
+                }
+                // Jobs run to completion and are not probed
+                if kind == "Job" {
+                    continue;
+                }
+                for (probe, field) in &[("liveness", container.liveness), ("readiness", container.readiness)] {
+                    if field.is_none_or(Value::is_null) {
+                        finding(Rule::Probes, format!("{} has no {} probe", container.description, probe));
+                    }
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// The parts of an Application spec or a container in a pod template the rules look at
+struct Container<'a> {
+    /// `the application` or `container <name>`, for the messages
+    description: String,
+    image: Option<&'a str>,
+    liveness: Option<&'a Value>,
+    readiness: Option<&'a Value>,
+}
+
+fn containers(resource: &Value) -> Vec<Container<'_>> {
+    let kind = resource.get("kind").and_then(Value::as_str).unwrap_or("");
+    if kind == "Application" && resource.get("apiVersion").and_then(Value::as_str) == Some("nais.io/v1alpha1") {
+        return vec![Container {
+            description: "the application".to_owned(),
+            image: resource.pointer("/spec/image").and_then(Value::as_str),
+            liveness: resource.pointer("/spec/liveness"),
+            readiness: resource.pointer("/spec/readiness"),
+        }];
+    }
+    if !WORKLOAD_KINDS.contains(&kind) {
+        return vec![];
+    }
+    resource.pointer("/spec/template/spec/containers")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|container| Container {
+            description: format!("container {}", container.get("name").and_then(Value::as_str).unwrap_or("<unnamed>")),
+            image: container.get("image").and_then(Value::as_str),
+            liveness: container.get("livenessProbe"),
+            readiness: container.get("readinessProbe"),
+        })
+        .collect()
+}
+
+/// The tag of an image reference, None if it has neither a tag nor a digest
+fn image_tag(image: &str) -> Option<&str> {
+    if let Some(index) = image.find('@') {
+        return Some(&image[index..]);
+    }
+    let name = image.rsplit('/').next().unwrap_or(image);
+    name.find(':')
+        .map(|index| &name[index + 1..])
+        .filter(|tag| !tag.is_empty())
+}
+
+/// Lints the resources according to --lint-level, printing findings as warnings or failing on them
+pub fn check_resources(subcommand: &ArgMatches, resources: &[Value]) -> Result<(), Error> {
+    let level: LintLevel = subcommand.value_of("lint-level").unwrap().parse()?;
+    if level == LintLevel::Off {
+        return Ok(());
+    }
+    let findings = Linter::from_args(subcommand)?.lint(resources);
+    if level == LintLevel::Error && !findings.is_empty() {
+        return Err(LintErrors { findings }.into());
+    }
+    for finding in &findings {
+        eprintln!("Warning: {}", finding);
+    }
+    Ok(())
+}
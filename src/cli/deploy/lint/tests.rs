@@ -0,0 +1,88 @@
+use serde_json::json;
+
+use super::{image_tag, Finding, Linter, Rule};
+
+fn application(namespace: &str, team: &str, image: &str) -> serde_json::Value {
+    json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application",
+        "metadata": {"name": "testapp", "namespace": namespace, "labels": {"team": team}},
+        "spec": {"image": image, "liveness": {"path": "/is_alive"}, "readiness": {"path": "/is_ready"}}})
+}
+
+fn messages(findings: Vec<Finding>) -> Vec<String> {
+    findings.iter().map(Finding::to_string).collect()
+}
+
+#[test]
+fn test_clean_application() {
+    let linter = Linter { team: "plattform", cluster: "dev-gcp", disabled: vec![] };
+    assert_eq!(Vec::<Finding>::new(), linter.lint(&[application("plattform", "plattform", "navikt/testapp:1.0.0")]));
+}
+
+#[test]
+fn test_team_label_and_namespace() {
+    let linter = Linter { team: "plattform", cluster: "dev-gcp", disabled: vec![] };
+    assert_eq!(vec![
+        "[team-label] Application/testapp: metadata.labels.team is aura, but the deployment is for the team plattform",
+        "[team-namespace] Application/testapp: metadata.namespace is default, but resources on dev-gcp belong in the team namespace plattform",
+    ], messages(linter.lint(&[application("default", "aura", "navikt/testapp:1.0.0")])));
+
+    let linter = Linter { team: "plattform", cluster: "prod-fss", disabled: vec![] };
+    assert_eq!(Vec::<Finding>::new(), linter.lint(&[application("default", "plattform", "navikt/testapp:1.0.0")]),
+               "The team namespace is only required on GCP clusters");
+}
+
+#[test]
+fn test_cluster_scoped_kinds_have_no_namespace() {
+    let resources = vec![
+        json!({"apiVersion": "v1", "kind": "Namespace", "metadata": {"name": "plattform"}}),
+        json!({"apiVersion": "rbac.authorization.k8s.io/v1", "kind": "ClusterRole", "metadata": {"name": "testapp"}}),
+        json!({"apiVersion": "apiextensions.k8s.io/v1", "kind": "CustomResourceDefinition", "metadata": {"name": "tests.nais.io"}}),
+        json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "testapp"}}),
+    ];
+    let linter = Linter { team: "plattform", cluster: "dev-gcp", disabled: vec![] };
+    assert_eq!(vec![
+        "[team-namespace] ConfigMap/testapp: metadata.namespace is not set, resources on dev-gcp belong in the team namespace plattform",
+    ], messages(linter.lint(&resources)));
+}
+
+#[test]
+fn test_image_tags() {
+    assert_eq!(Some("1.0.0"), image_tag("navikt/testapp:1.0.0"));
+    assert_eq!(Some("1.0.0"), image_tag("docker.pkg.github.com:443/navikt/testapp:1.0.0"));
+    assert_eq!(None, image_tag("docker.pkg.github.com:443/navikt/testapp"));
+    assert_eq!(None, image_tag("navikt/testapp:"));
+    assert_eq!(Some("@sha256:abc"), image_tag("navikt/testapp@sha256:abc"));
+
+    let linter = Linter { team: "plattform", cluster: "prod-fss", disabled: vec![] };
+    assert_eq!(vec![
+        "[image-tag] Application/testapp: the application uses the latest tag of navikt/testapp:latest, deploy a fixed version instead",
+        "[image-tag] Application/testapp: the application uses navikt/testapp without a tag, deploy a fixed version instead",
+    ], messages(linter.lint(&[application("default", "plattform", "navikt/testapp:latest"), application("default", "plattform", "navikt/testapp")])));
+}
+
+#[test]
+fn test_workload_containers() {
+    let deployment = json!({"apiVersion": "apps/v1", "kind": "Deployment", "metadata": {"name": "testapp"},
+        "spec": {"template": {"spec": {"containers": [
+            {"name": "app", "image": "navikt/testapp:1.0.0", "livenessProbe": {"httpGet": {"path": "/"}}},
+            {"name": "sidecar"},
+        ]}}}});
+    let job = json!({"apiVersion": "batch/v1", "kind": "Job", "metadata": {"name": "migrate"},
+        "spec": {"template": {"spec": {"containers": [{"name": "migrate", "image": "navikt/migrate:1"}]}}}});
+    let linter = Linter { team: "plattform", cluster: "prod-fss", disabled: vec![] };
+    assert_eq!(vec![
+        "[probes] Deployment/testapp: container app has no readiness probe",
+        "[image-tag] Deployment/testapp: container sidecar has no image",
+        "[probes] Deployment/testapp: container sidecar has no liveness probe",
+        "[probes] Deployment/testapp: container sidecar has no readiness probe",
+    ], messages(linter.lint(&[deployment, job])));
+}
+
+#[test]
+fn test_disabled_rules() {
+    let mut resource = application("default", "aura", "navikt/testapp:latest");
+    resource["spec"].as_object_mut().unwrap().remove("liveness");
+    let linter = Linter { team: "plattform", cluster: "dev-gcp", disabled: vec![Rule::TeamLabel, Rule::ImageTag, Rule::Probes] };
+    let rules: Vec<Rule> = linter.lint(&[resource]).into_iter().map(|finding| finding.rule).collect();
+    assert_eq!(vec![Rule::TeamNamespace], rules);
+}
@@ -1,25 +1,41 @@
 mod create;
+mod crypto;
+mod diagnostics;
+mod input;
+mod lint;
+mod patches;
+mod policy;
+mod resources;
+mod sanity;
+mod schema;
+mod secrets;
+mod template;
+mod vars;
+mod watch;
 #[cfg(test)]
 mod tests;
 
-use std::fs::{OpenOptions, File};
-use std::io::Read;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
 
 use clap::ArgMatches;
 use failure::{Error, ResultExt};
+use glob::Pattern;
 use handlebars::Handlebars;
 use serde_json::Value;
 
 use crate::models::{DeploymentRequest, Kubernetes, Payload};
+use self::crypto::{Identities, LazyIdentities};
+use self::patches::SetOverride;
+use self::resources::ResourceFormat;
+use self::template::{MissingVariable, UndefinedVariables, VariableReference};
+use self::vars::{Vars, VarsFormat, VarsReport};
 
-pub fn handle_deploy_command(subcommand: &ArgMatches) -> Result<(), Error> {
-    let mut config: Value = if let Some(config_path) = subcommand.value_of("variables") {
-        let file = File::open(config_path).context(format!("Unable to open resource file {}", config_path))?;
-        serde_json::from_reader(file).context(format!("Unable to parse json config {}", config_path))?
-    } else {
-        Value::Null
-    };
+pub use self::lint::{LINT_LEVELS, LINT_RULES};
+pub use self::vars::VARS_FORMATS;
 
+pub fn handle_deploy_command(subcommand: &ArgMatches) -> Result<(), Error> {
     let git_ref = subcommand.value_of("ref").unwrap();
     let cluster = subcommand.value_of("cluster").unwrap();
     let auto_merge: bool = subcommand.value_of("auto-merge").unwrap()
@@ -27,43 +43,34 @@ pub fn handle_deploy_command(subcommand: &ArgMatches) -> Result<(), Error> {
         .unwrap();
     let team = subcommand.value_of("team")
         .ok_or(format_err!("To create a deployment you need to specify a team"))?;
+    input::check_stdin_arguments(subcommand, input::DEPLOY_STDIN_ARGUMENTS)?;
 
-    config["ref"] = Value::String(git_ref.to_owned());
-    config["cluster"] = Value::String(cluster.to_owned());
-    config["team"] = Value::String(team.to_owned());
-
-    if let Some(version) = subcommand.value_of("version") {
-        config["version"] = Value::String(version.to_owned());
+    if let Some(payload_subcmd) = subcommand.subcommand_matches("payload") {
+        if payload_subcmd.is_present("watch") {
+            return watch::watch_payload(subcommand, payload_subcmd);
+        }
     }
 
-    if let Some(overrides) = subcommand.values_of("var") {
-        for var in overrides {
-            let equals_index = var.find('=')
-                .ok_or(format_err!("Invalid format for variable override, expected <name>=<value>"))?;
-            let key = &var[0..equals_index];
-            let value = &var[equals_index+1..];
-            config[key] = Value::String(value.to_owned());
-        }
+    let vars = assemble_vars(subcommand, git_ref, cluster, team)?;
+    if subcommand.subcommand_matches("vars").is_some() {
+        let references = referenced_variables(subcommand)?;
+        print!("{}", VarsReport::new(&vars, &references));
+        return Ok(());
     }
 
-    let resources = get_resources(subcommand, &config);
+    let deployment_payload = build_deployment_request(subcommand, &vars, auto_merge)?;
 
-    let deployment_payload = DeploymentRequest {
-        git_ref: git_ref.to_owned(),
-        auto_merge: auto_merge,
-        description: format!("Automated deployment request to {}", cluster),
-        environment: cluster.to_owned(),
-        required_contexts: vec![],
-        payload: Payload {
-            version: vec![1, 0, 0],
-            team: team.to_owned(),
-            kubernetes: Kubernetes {
-                resources: resources?
-            }
+    if let Some(render_command) = subcommand.subcommand_matches("render") {
+        let out_dir = render_command.value_of("out-dir").unwrap();
+        for path in resources::write_resources(out_dir, &deployment_payload.payload.kubernetes.resources)? {
+            println!("Wrote {}", path.display());
         }
-    };
+    }
 
     if let Some(payload_subcmd) = subcommand.subcommand_matches("payload") {
+        if payload_subcmd.is_present("explain-vars") {
+            eprint!("{}", vars);
+        }
         if let Some(output_file) = payload_subcmd.value_of("outputfile") {
             let file = OpenOptions::new()
                 .write(true)
@@ -82,47 +89,296 @@ pub fn handle_deploy_command(subcommand: &ArgMatches) -> Result<(), Error> {
     Ok(())
 }
 
-fn get_resources(subcommand: &ArgMatches, config: &Value) -> Result<Vec<Value>, Error> {
-    let reg = Handlebars::new();
+/// Validates the variables and renders the resources into the deployment request
+fn build_deployment_request(subcommand: &ArgMatches, vars: &Vars, auto_merge: bool) -> Result<DeploymentRequest, Error> {
+    let git_ref = subcommand.value_of("ref").unwrap();
+    let cluster = subcommand.value_of("cluster").unwrap();
+    let team = subcommand.value_of("team").unwrap();
 
-    let resource_matches: Vec<&str> = if let Some(values) = subcommand.values_of("resource") {
-        values.collect()
+    if let Some(schema_path) = subcommand.value_of("vars-schema") {
+        schema::validate_vars(schema_path, &vars.value)?;
+    }
+    let config = &vars.value;
+
+    let resources = get_resources(subcommand, config)?;
+    sanity::check_resources(&resources)?;
+    lint::check_resources(subcommand, &resources)?;
+
+    let request = DeploymentRequest {
+        git_ref: git_ref.to_owned(),
+        auto_merge: auto_merge,
+        description: format!("Automated deployment request to {}", cluster),
+        environment: cluster.to_owned(),
+        required_contexts: vec![],
+        payload: Payload {
+            version: vec![1, 0, 0],
+            team: team.to_owned(),
+            kubernetes: Kubernetes {
+                resources
+            }
+        }
+    };
+
+    if let Some(policy_path) = subcommand.value_of("policy") {
+        policy::enforce(policy_path, &request)?;
+    }
+    // Rendered files stay on the local disk, only payloads sent to or printed for GitHub are scanned for secrets
+    if subcommand.subcommand_matches("render").is_none() {
+        secrets::check_resources(subcommand, &request.payload.kubernetes.resources)?;
+    }
+    Ok(request)
+}
+
+/// Encrypts or decrypts a vars file with age, for editing encrypted vars files
+pub fn handle_vars_command(vars_command: &ArgMatches) -> Result<(), Error> {
+    if let (_, Some(command)) = vars_command.subcommand() {
+        input::check_stdin_arguments(command, input::VARS_STDIN_ARGUMENTS)?;
+    }
+
+    let (file, output) = if let Some(encrypt_command) = vars_command.subcommand_matches("encrypt") {
+        let mut recipients = encrypt_command.values_of("recipient").into_iter()
+            .flatten()
+            .map(crypto::parse_recipient)
+            .collect::<Result<Vec<_>, Error>>()?;
+        if recipients.is_empty() {
+            if let Some(identities) = Identities::from_args(encrypt_command)? {
+                recipients = identities.recipients();
+            }
+        }
+        if recipients.is_empty() {
+            return Err(format_err!("Pass the public keys to encrypt to with --recipient, or an age identity to encrypt to its public keys"));
+        }
+
+        let file = encrypt_command.value_of("file").unwrap();
+        let plaintext = input::read_bytes(file)
+            .context(format!("Unable to read vars file {}", file))?;
+        (encrypt_command, crypto::encrypt(plaintext.as_slice(), &recipients)?)
+    } else if let Some(decrypt_command) = vars_command.subcommand_matches("decrypt") {
+        let identities = Identities::from_args(decrypt_command)?
+            .ok_or_else(|| format_err!("Pass an age identity file with --age-identity or the identity in {} to decrypt", crypto::IDENTITY_ENV))?;
+
+        let file = decrypt_command.value_of("file").unwrap();
+        let encrypted = input::read_bytes(file)
+            .context(format!("Unable to read vars file {}", file))?;
+        let plaintext = crypto::decrypt(encrypted.as_slice(), &identities)
+            .context(format!("Unable to decrypt vars file {}", file))?;
+        (decrypt_command, plaintext)
     } else {
-        vec![]
+        return Err(format_err!("Failed to execute command: Could not match vars subcommand, this is a bug."));
     };
 
-    let raw_resource_matches: Vec<&str> = if let Some(values) = subcommand.values_of("raw-resource") {
-        values.collect()
+    if let Some(output_file) = file.value_of("outputfile") {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(output_file)
+            .and_then(|mut file| file.write_all(output.as_slice()))
+            .context(format!("Failed to write to output file {}", output_file))?;
     } else {
-        vec![]
+        std::io::stdout().write_all(output.as_slice())
+            .context("Failed to write to stdout")?;
+    }
+    Ok(())
+}
+
+/// Builds the templating variables, later sources take precedence: common and cluster files from --vars-dir, the
+/// --vars files in the order given, environment variables matching --env-prefix, the built-in ref/cluster/team
+/// values, the deprecated --version flag and finally --var overrides
+fn assemble_vars(subcommand: &ArgMatches, git_ref: &str, cluster: &str, team: &str) -> Result<Vars, Error> {
+    let vars_format = match subcommand.value_of("vars-format") {
+        Some(format) => Some(format.parse::<VarsFormat>()?),
+        None => None,
     };
 
-    let mut result: Vec<Value> = Vec::new();
-    for file_name in resource_matches {
-        let mut file = File::open(file_name)
-            .context(format!("Unable to open placeholder file {}", file_name))?;
+    let mut config_paths: Vec<(String, Option<VarsFormat>)> = vec![];
+    if let Some(vars_dir) = subcommand.value_of("vars-dir") {
+        if let Some(common) = vars::find_vars_file(vars_dir, "common")? {
+            config_paths.push((common.display().to_string(), None));
+        }
+        match vars::find_vars_file(vars_dir, cluster)? {
+            Some(cluster_vars) => config_paths.push((cluster_vars.display().to_string(), None)),
+            None if subcommand.is_present("require-cluster-vars") =>
+                return Err(format_err!("Could not find a vars file for the cluster {} in {}, expected {}/{}.<json|yaml|yml|toml|env>", cluster, vars_dir, vars_dir, cluster)),
+            None => {},
+        }
+    }
+    if let Some(paths) = subcommand.values_of("variables") {
+        config_paths.extend(paths.map(|path| (path.to_owned(), vars_format)));
+    }
+
+    let mut identities = LazyIdentities::new(subcommand);
+    let mut vars = Vars::new();
+    for (config_path, format) in config_paths {
+        match vars::load_vars(config_path.as_str(), format, &mut identities)? {
+            Value::Null => {},
+            value @ Value::Object(_) => vars.merge(value, config_path.as_str()),
+            _ => return Err(format_err!("Vars file {} has to contain a mapping of variable names to values", config_path)),
+        }
+    }
+
+    if let Some(prefix) = subcommand.value_of("env-prefix") {
+        for (path, value, name) in vars::env_overrides(prefix, std::env::vars()) {
+            vars.set_path(path.as_str(), Value::String(value), format!("env {}", name).as_str())?;
+        }
+    }
+
+    vars.set("ref", Value::String(git_ref.to_owned()), "built-in");
+    vars.set("cluster", Value::String(cluster.to_owned()), "built-in");
+    vars.set("team", Value::String(team.to_owned()), "built-in");
+
+    if let Some(version) = subcommand.value_of("version") {
+        vars.set("version", Value::String(version.to_owned()), "--version");
+    }
+
+    if let Some(overrides) = subcommand.values_of("var") {
+        for var in overrides {
+            let (path, value) = vars::parse_override(var)?;
+            vars.set_path(path, value, "--var")?;
+        }
+    }
+    Ok(vars)
+}
+
+fn get_resources(subcommand: &ArgMatches, config: &Value) -> Result<Vec<Value>, Error> {
+    let strict: bool = subcommand.value_of("strict").unwrap()
+        .parse()
+        .unwrap();
+    let reg = create_registry(subcommand, strict)?;
+
+    let resource_files = expand_resource_argument(subcommand, "resource")?;
+    let raw_resource_files = expand_resource_argument(subcommand, "raw-resource")?;
 
-        let mut resource_template = String::new();
-        file.read_to_string(&mut resource_template)
-            .context(format!("Failed to read resource file {}", file_name))?;
+    let mut result: Vec<Value> = Vec::new();
+    let mut missing_variables: Vec<MissingVariable> = Vec::new();
+    for file_name in resource_files.iter().map(String::as_str) {
+        let format = ResourceFormat::from_path(file_name);
+        if format == ResourceFormat::Jsonnet {
+            result.extend(resources::evaluate_jsonnet(file_name, Some(config))?);
+            continue;
+        }
 
-        let resource = reg.render_template(resource_template.as_str(), config)
-            .context(format!("Failed to render template for file {}", file_name))?;
+        let resource_template = input::read_to_string(file_name)
+            .context(format!("Unable to read placeholder file {}", file_name))?;
 
-        let value: Value = serde_yaml::from_str(resource.as_str()).context(format!("Failed to parse json for {}", file_name))?;
+        template::check_partials(&reg, file_name, resource_template.as_str())?;
 
-        if let Some(values) = value.as_array() {
-            result.extend_from_slice(values.as_slice());
+        let resource = if strict {
+            match template::render_strict(&reg, file_name, resource_template.as_str(), config, &mut missing_variables)? {
+                Some(resource) => resource,
+                None => continue,
+            }
         } else {
-            result.push(value);
+            reg.render_template(resource_template.as_str(), config)
+                .map_err(|e| template::render_error(file_name, resource_template.as_str(), e))?
+        };
+
+        let parsed = resources::parse_resources(resource.as_str(), format, file_name)
+            .map_err(|e| diagnostics::locate_parse_error(e, file_name, Some(resource_template.as_str()), resource.as_str()))?;
+        result.extend(parsed);
+    }
+
+    if !missing_variables.is_empty() {
+        return Err(UndefinedVariables { missing: missing_variables }.into());
+    }
+
+    for file_name in raw_resource_files.iter().map(String::as_str) {
+        let format = ResourceFormat::from_path(file_name);
+        if format == ResourceFormat::Jsonnet {
+            result.extend(resources::evaluate_jsonnet(file_name, None)?);
+            continue;
         }
+
+        let raw_resource = input::read_to_string(file_name)
+            .context(format!("Unable to read resource file {}", file_name))?;
+
+        let parsed = resources::parse_resources(raw_resource.as_str(), format, file_name)
+            .map_err(|e| diagnostics::locate_parse_error(e, file_name, None, raw_resource.as_str()))?;
+        result.extend(parsed);
+    }
+
+    for patch_file in patch_files(subcommand)? {
+        let patches = patches::load_patches(patch_file.as_str())?;
+        patches::apply_patches(&mut result, &patches, patch_file.as_str())?;
     }
 
-    for file_name in raw_resource_matches {
-        let file = File::open(file_name)?;
+    if let Some(sets) = subcommand.values_of("set") {
+        for set in sets {
+            SetOverride::parse(set)?.apply(&mut result)?;
+        }
+    }
 
-        let value: Value = serde_yaml::from_reader(&file)?;
-        result.push(value)
+    let validate_resources: bool = subcommand.value_of("validate-resources").unwrap()
+        .parse()
+        .unwrap();
+    if validate_resources {
+        schema::validate_resources(&result)?;
     }
     Ok(result)
 }
+
+fn create_registry(subcommand: &ArgMatches, strict: bool) -> Result<Handlebars, Error> {
+    let mut reg = template::create_registry(strict);
+    if let Some(partials_dir) = subcommand.value_of("partials") {
+        template::register_partials(&mut reg, partials_dir)?;
+    }
+    Ok(reg)
+}
+
+/// Every variable referenced by the --resource templates, in the order of the files
+fn referenced_variables(subcommand: &ArgMatches) -> Result<Vec<VariableReference>, Error> {
+    let reg = create_registry(subcommand, true)?;
+    let mut references = vec![];
+    for file_name in expand_resource_argument(subcommand, "resource")? {
+        let content = input::read_to_string(file_name.as_str())
+            .context(format!("Unable to read placeholder file {}", file_name))?;
+        if ResourceFormat::from_path(file_name.as_str()) == ResourceFormat::Jsonnet {
+            references.extend(resources::jsonnet_ext_vars(file_name.as_str(), content.as_str()));
+        } else {
+            references.extend(template::referenced_variables(&reg, file_name.as_str(), content.as_str())?);
+        }
+    }
+    Ok(references)
+}
+
+fn expand_resource_argument(subcommand: &ArgMatches, argument: &str) -> Result<Vec<String>, Error> {
+    let matches: Vec<&str> = if let Some(values) = subcommand.values_of(argument) {
+        values.collect()
+    } else {
+        vec![]
+    };
+    let excludes = resources::exclude_patterns(subcommand.values_of("exclude").into_iter().flatten())?;
+    expand_resource_inputs(matches, &excludes)
+}
+
+/// Patch files from the --overlay-dir directory for the cluster, if it exists, followed by --patch files
+fn patch_files(subcommand: &ArgMatches) -> Result<Vec<String>, Error> {
+    let mut files = vec![];
+    if let Some(overlay_dir) = subcommand.value_of("overlay-dir") {
+        if !Path::new(overlay_dir).is_dir() {
+            return Err(format_err!("The overlay directory {} does not exist", overlay_dir));
+        }
+        // Clusters without overlays do not need a directory
+        let cluster = subcommand.value_of("cluster").unwrap();
+        let cluster_dir = Path::new(overlay_dir).join(cluster);
+        if cluster_dir.is_dir() {
+            files.extend(resources::expand_input(cluster_dir.to_string_lossy().as_ref(), &[])?);
+        }
+    }
+    if let Some(patches) = subcommand.values_of("patch") {
+        files.extend(patches.map(str::to_owned));
+    }
+    Ok(files)
+}
+
+fn expand_resource_inputs(arguments: Vec<&str>, excludes: &[Pattern]) -> Result<Vec<String>, Error> {
+    let mut files = vec![];
+    for argument in arguments {
+        let expanded = resources::expand_input(argument, excludes)?;
+        if expanded.len() != 1 || expanded[0] != argument {
+            eprintln!("Resources from {}: {}", argument, expanded.join(", "));
+        }
+        files.extend(expanded);
+    }
+    Ok(files)
+}
@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod tests;
+
+use std::fs::File;
+use std::io::Read;
+
+use failure::{Error, ResultExt};
+use json_patch::Patch;
+use serde_json::Value;
+
+use crate::cli::deploy::resources::{self, ResourceFormat};
+use crate::cli::deploy::vars;
+
+/// Identifies the resources a patch applies to, the apiVersion is optional
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchTarget {
+    pub api_version: Option<String>,
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourcePatch {
+    /// RFC 7386 merge patch, written as a partial resource with apiVersion, kind and metadata.name
+    Merge { target: PatchTarget, patch: Value },
+    /// RFC 6902 JSON patch, written as a `target` and a list of operations in `patch`
+    Json { target: PatchTarget, patch: Patch },
+}
+
+impl ResourcePatch {
+    pub fn target(&self) -> &PatchTarget {
+        match self {
+            ResourcePatch::Merge { target, .. } | ResourcePatch::Json { target, .. } => target,
+        }
+    }
+
+    pub fn from_value(value: Value) -> Result<ResourcePatch, Error> {
+        if let Some(target) = value.get("target") {
+            let target: PatchTarget = serde_json::from_value(target.clone())
+                .context("A JSON patch target needs a kind and a name")?;
+            let patch = value.get("patch")
+                .cloned()
+                .ok_or_else(|| format_err!("JSON patch for {}/{} has no operations in patch", target.kind, target.name))?;
+            let patch = json_patch::from_value(patch)
+                .context(format!("Invalid JSON patch operations for {}/{}", target.kind, target.name))?;
+            Ok(ResourcePatch::Json { target, patch })
+        } else {
+            let target = PatchTarget {
+                api_version: value.get("apiVersion").and_then(Value::as_str).map(str::to_owned),
+                kind: value.get("kind")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format_err!("A merge patch needs a kind, or a target if it is a JSON patch"))?
+                    .to_owned(),
+                name: value.pointer("/metadata/name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format_err!("A merge patch needs a metadata.name"))?
+                    .to_owned(),
+            };
+            Ok(ResourcePatch::Merge { target, patch: value })
+        }
+    }
+
+    fn matches(&self, resource: &Value) -> bool {
+        let target = self.target();
+        resource.get("kind").and_then(Value::as_str) == Some(target.kind.as_str())
+            && resource.pointer("/metadata/name").and_then(Value::as_str) == Some(target.name.as_str())
+            && target.api_version.as_ref().is_none_or(|api_version| resource.get("apiVersion").and_then(Value::as_str) == Some(api_version.as_str()))
+    }
+
+    fn apply(&self, resource: &mut Value) -> Result<(), Error> {
+        match self {
+            ResourcePatch::Merge { patch, .. } => json_patch::merge(resource, patch),
+            ResourcePatch::Json { patch, .. } => json_patch::patch(resource, patch)?,
+        }
+        Ok(())
+    }
+}
+
+/// Reads the patches in a YAML or JSON file, a file can contain several patches as multiple documents or a list
+pub fn load_patches(file_name: &str) -> Result<Vec<ResourcePatch>, Error> {
+    let mut content = String::new();
+    File::open(file_name)
+        .context(format!("Unable to open patch file {}", file_name))?
+        .read_to_string(&mut content)
+        .context(format!("Failed to read patch file {}", file_name))?;
+
+    let format = match ResourceFormat::from_path(file_name) {
+        ResourceFormat::Json => ResourceFormat::Json,
+        _ => ResourceFormat::Yaml,
+    };
+    resources::parse_resources(content.as_str(), format, file_name)?
+        .into_iter()
+        .map(|value| ResourcePatch::from_value(value)
+            .context(format!("Invalid patch in {}", file_name))
+            .map_err(Error::from))
+        .collect()
+}
+
+/// Applies every patch to all resources matching its target, a patch matching no resources is an error
+pub fn apply_patches(resources: &mut [Value], patches: &[ResourcePatch], file_name: &str) -> Result<(), Error> {
+    for patch in patches {
+        let target = patch.target();
+        let mut matched = false;
+        for resource in resources.iter_mut().filter(|resource| patch.matches(resource)) {
+            patch.apply(resource)
+                .context(format!("Failed to apply patch for {}/{} from {}", target.kind, target.name, file_name))?;
+            matched = true;
+        }
+        if !matched {
+            return Err(format_err!("Patch for {}/{} from {} does not match any resource", target.kind, target.name, file_name));
+        }
+    }
+    Ok(())
+}
+
+/// Sets a single existing field in a resource, parsed from `<kind>/<name>:<path>=<value>` or `<kind>/<name>:<path>:=<json>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetOverride {
+    pub kind: String,
+    pub name: String,
+    pub path: String,
+    pub value: Value,
+}
+
+impl SetOverride {
+    pub fn parse(set: &str) -> Result<SetOverride, Error> {
+        let invalid_format = || format_err!("Invalid format for --set {}, expected <kind>/<name>:<path>=<value>", set);
+        let colon_index = set.find(':').ok_or_else(invalid_format)?;
+        let mut selector = set[..colon_index].splitn(2, '/');
+        let kind = selector.next().filter(|kind| !kind.is_empty()).ok_or_else(invalid_format)?;
+        let name = selector.next().filter(|name| !name.is_empty()).ok_or_else(invalid_format)?;
+        let (path, value) = vars::parse_override(&set[colon_index + 1..])?;
+
+        Ok(SetOverride { kind: kind.to_owned(), name: name.to_owned(), path: path.to_owned(), value })
+    }
+
+    /// Applies the override to every resource with the kind and name, the resource and the path have to exist
+    pub fn apply(&self, resources: &mut [Value]) -> Result<(), Error> {
+        let mut matched = false;
+        for resource in resources.iter_mut() {
+            if resource.get("kind").and_then(Value::as_str) != Some(self.kind.as_str())
+                || resource.pointer("/metadata/name").and_then(Value::as_str) != Some(self.name.as_str()) {
+                continue;
+            }
+            let field = self.path.split('.')
+                .try_fold(resource, |current, key| match current {
+                    Value::Object(map) => map.get_mut(key),
+                    Value::Array(values) => key.parse::<usize>().ok().and_then(move |i| values.get_mut(i)),
+                    _ => None,
+                })
+                .ok_or_else(|| format_err!("{}/{} has no field {} to --set", self.kind, self.name, self.path))?;
+            *field = self.value.clone();
+            matched = true;
+        }
+        if matched {
+            Ok(())
+        } else {
+            Err(format_err!("Could not find the resource {}/{} to --set {}", self.kind, self.name, self.path))
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use serde_json::json;
+
+use super::{apply_patches, load_patches, ResourcePatch, SetOverride};
+
+fn resources() -> Vec<serde_json::Value> {
+    vec![
+        json!({"apiVersion": "v1", "kind": "ServiceAccount", "metadata": {"name": "testapp"}}),
+        json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application", "metadata": {"name": "testapp"}, "spec": {"replicas": {"min": 1, "max": 4, "cpuThresholdPercentage": 70}}}),
+    ]
+}
+
+#[test]
+fn test_merge_patch() {
+    let mut resources = resources();
+    let patches = assert_ok!(load_patches("testdata/overlays/prod-fss/replicas.yaml"));
+    assert_ok!(apply_patches(&mut resources, &patches, "replicas.yaml"));
+
+    assert_eq!(json!({"min": 2, "max": 4}), resources[1]["spec"]["replicas"]);
+    assert_eq!(json!({"apiVersion": "v1", "kind": "ServiceAccount", "metadata": {"name": "testapp"}}), resources[0]);
+}
+
+#[test]
+fn test_json_patch() {
+    let mut resources = resources();
+    let patches = assert_ok!(load_patches("testdata/patch_ingress.yaml"));
+    assert_ok!(apply_patches(&mut resources, &patches, "patch_ingress.yaml"));
+
+    assert_eq!(json!(["https://testapp.nais.adeo.no"]), resources[1]["spec"]["ingresses"]);
+    assert_eq!(json!(8), resources[1]["spec"]["replicas"]["max"]);
+}
+
+#[test]
+fn test_patch_without_matching_resource() {
+    let mut resources = resources();
+    let patch = assert_ok!(ResourcePatch::from_value(json!({"apiVersion": "v1", "kind": "Application", "metadata": {"name": "testapp"}})));
+
+    let error = apply_patches(&mut resources, &[patch], "patch.yaml").unwrap_err();
+    assert_eq!("Patch for Application/testapp from patch.yaml does not match any resource", error.to_string());
+}
+
+#[test]
+fn test_invalid_patches() {
+    assert!(ResourcePatch::from_value(json!({"kind": "Application"})).is_err());
+    assert!(ResourcePatch::from_value(json!({"target": {"kind": "Application"}, "patch": []})).is_err());
+    assert!(ResourcePatch::from_value(json!({"target": {"kind": "Application", "name": "testapp"}, "patch": [{"op": "explode"}]})).is_err());
+}
+
+#[test]
+fn test_parse_set() {
+    assert_eq!(SetOverride { kind: "Application".to_owned(), name: "testapp".to_owned(), path: "spec.replicas.min".to_owned(), value: json!(2) },
+               assert_ok!(SetOverride::parse("Application/testapp:spec.replicas.min:=2")));
+    assert_eq!(SetOverride { kind: "Application".to_owned(), name: "testapp".to_owned(), path: "spec.image".to_owned(), value: json!("navikt/testapp:a:b") },
+               assert_ok!(SetOverride::parse("Application/testapp:spec.image=navikt/testapp:a:b")));
+    assert!(SetOverride::parse("Application:spec.image=x").is_err());
+    assert!(SetOverride::parse("Application/testapp=x").is_err());
+}
+
+#[test]
+fn test_apply_set() {
+    let mut resources = resources();
+    assert_ok!(assert_ok!(SetOverride::parse("Application/testapp:spec.replicas.min:=3")).apply(&mut resources));
+    assert_eq!(json!(3), resources[1]["spec"]["replicas"]["min"]);
+
+    let missing_path = assert_ok!(SetOverride::parse("Application/testapp:spec.replicas.mni:=3")).apply(&mut resources).unwrap_err();
+    assert_eq!("Application/testapp has no field spec.replicas.mni to --set", missing_path.to_string());
+    let missing_resource = assert_ok!(SetOverride::parse("Application/other:spec.replicas.min:=3")).apply(&mut resources).unwrap_err();
+    assert_eq!("Could not find the resource Application/other to --set spec.replicas.min", missing_resource.to_string());
+}
@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use failure::{Error, ResultExt};
+use glob::Pattern;
+use serde_json::Value;
+
+use crate::cli::deploy::input;
+use crate::models::DeploymentRequest;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub name: String,
+    #[serde(default)]
+    pub action: Action,
+    #[serde(default)]
+    pub target: Target,
+    #[serde(default, rename = "match")]
+    pub matches: Match,
+    /// Conditions describing what the rule forbids, the rule applies when all of them hold
+    pub when: Vec<Condition>,
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Deny,
+    Warn,
+}
+
+/// Whether the conditions are evaluated for each resource or once for the whole deployment request
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    #[default]
+    Resources,
+    Request,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Match {
+    /// Glob patterns for the cluster, any cluster when empty
+    #[serde(default)]
+    pub clusters: Vec<String>,
+    /// Resource kinds, any kind when empty
+    #[serde(default)]
+    pub kinds: Vec<String>,
+}
+
+/// A test of the values at a dotted path, where `*` matches every element of an array or every value of a mapping.
+/// Exactly one of the operators has to be set
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    pub path: String,
+    pub equals: Option<Value>,
+    pub exists: Option<bool>,
+    pub greater_than: Option<f64>,
+    pub less_than: Option<f64>,
+    /// Glob patterns, the condition holds for a string matching any of them
+    pub matches: Option<Vec<String>>,
+    /// Glob patterns, the condition holds for a string matching none of them
+    pub not_matches: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyResult {
+    pub rule: String,
+    pub action: Action,
+    /// `request` or the `<kind>/<name>` of the resource
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.rule, self.target, self.message)
+    }
+}
+
+#[derive(Fail, Debug)]
+pub struct PolicyDenied {
+    pub policy: String,
+    pub results: Vec<PolicyResult>,
+}
+
+impl fmt::Display for PolicyDenied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The deployment is denied by the policy {}:", self.policy)?;
+        for result in &self.results {
+            write!(f, "\n  {}", result)?;
+        }
+        Ok(())
+    }
+}
+
+impl Policy {
+    pub fn load(path: &str) -> Result<Policy, Error> {
+        let content = input::read_to_string(path)
+            .context(format!("Unable to read policy file {}", path))?;
+        let policy: Policy = serde_yaml::from_str(content.as_str())
+            .context(format!("Unable to parse policy file {}", path))?;
+        for rule in &policy.rules {
+            rule.validate()
+                .context(format!("Invalid rule {} in policy file {}", rule.name, path))?;
+        }
+        Ok(policy)
+    }
+
+    /// Evaluates every rule against the request, in the order of the rules
+    pub fn evaluate(&self, request: &DeploymentRequest) -> Result<Vec<PolicyResult>, Error> {
+        let value = serde_json::to_value(request)?;
+        let mut results = vec![];
+        for rule in &self.rules {
+            if !matches_any(&rule.matches.clusters, request.environment.as_str())? {
+                continue;
+            }
+            let result = |target: String| PolicyResult { rule: rule.name.clone(), action: rule.action, target, message: rule.message.clone() };
+            match rule.target {
+                Target::Request => if rule.applies_to(&value)? {
+                    results.push(result("request".to_owned()));
+                },
+                Target::Resources => for resource in &request.payload.kubernetes.resources {
+                    let kind = resource.get("kind").and_then(Value::as_str).unwrap_or("");
+                    if !rule.matches.kinds.is_empty() && !rule.matches.kinds.iter().any(|k| k == kind) {
+                        continue;
+                    }
+                    if rule.applies_to(resource)? {
+                        let name = resource.pointer("/metadata/name").and_then(Value::as_str).unwrap_or("<unnamed>");
+                        results.push(result(format!("{}/{}", kind, name)));
+                    }
+                },
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl PolicyRule {
+    fn validate(&self) -> Result<(), Error> {
+        if self.when.is_empty() {
+            return Err(format_err!("The rule needs at least one condition in when"));
+        }
+        if self.target == Target::Request && !self.matches.kinds.is_empty() {
+            return Err(format_err!("match.kinds only applies to rules with the target resources"));
+        }
+        for pattern in &self.matches.clusters {
+            Pattern::new(pattern).context(format!("Invalid cluster pattern {}", pattern))?;
+        }
+        for condition in &self.when {
+            condition.validate()?;
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, value: &Value) -> Result<bool, Error> {
+        for condition in &self.when {
+            if !condition.holds(value)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Condition {
+    fn validate(&self) -> Result<(), Error> {
+        let operators = [self.equals.is_some(), self.exists.is_some(), self.greater_than.is_some(),
+            self.less_than.is_some(), self.matches.is_some(), self.not_matches.is_some()];
+        if operators.iter().filter(|set| **set).count() != 1 {
+            return Err(format_err!("The condition on {} needs exactly one of equals, exists, greater_than, less_than, matches and not_matches", self.path));
+        }
+        for pattern in self.matches.iter().chain(self.not_matches.iter()).flatten() {
+            Pattern::new(pattern).context(format!("Invalid pattern {} in the condition on {}", pattern, self.path))?;
+        }
+        Ok(())
+    }
+
+    /// Whether any of the values at the path fulfills the condition
+    fn holds(&self, value: &Value) -> Result<bool, Error> {
+        for found in resolve(value, self.path.as_str()) {
+            let found = found.filter(|v| !v.is_null());
+            let holds = if let Some(expected) = &self.equals {
+                found == Some(expected)
+            } else if let Some(exists) = self.exists {
+                found.is_some() == exists
+            } else if let Some(limit) = self.greater_than {
+                found.and_then(Value::as_f64).is_some_and(|n| n > limit)
+            } else if let Some(limit) = self.less_than {
+                found.and_then(Value::as_f64).is_some_and(|n| n < limit)
+            } else if let Some(patterns) = &self.matches {
+                match found.and_then(Value::as_str) {
+                    Some(s) => matches_any(patterns, s)?,
+                    None => false,
+                }
+            } else if let Some(patterns) = &self.not_matches {
+                match found.and_then(Value::as_str) {
+                    Some(s) => !matches_any(patterns, s)?,
+                    None => false,
+                }
+            } else {
+                false
+            };
+            if holds {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Whether the value matches any of the glob patterns, or the patterns are empty
+fn matches_any(patterns: &[String], value: &str) -> Result<bool, Error> {
+    if patterns.is_empty() {
+        return Ok(true);
+    }
+    for pattern in patterns {
+        if Pattern::new(pattern)?.matches(value) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The values at a dotted path, with None where the path does not exist
+fn resolve<'a>(value: &'a Value, path: &str) -> Vec<Option<&'a Value>> {
+    let mut current = vec![Some(value)];
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        current = current.into_iter()
+            .flat_map(|value| match (value, segment) {
+                (Some(Value::Array(items)), "*") => items.iter().map(Some).collect(),
+                (Some(Value::Object(map)), "*") => map.values().map(Some).collect(),
+                (Some(Value::Array(items)), index) => vec![index.parse::<usize>().ok().and_then(|i| items.get(i))],
+                (Some(Value::Object(map)), key) => vec![map.get(key)],
+                _ => vec![None],
+            })
+            .collect();
+    }
+    current
+}
+
+/// Evaluates the --policy file against the request, printing warnings and failing on any denial
+pub fn enforce(path: &str, request: &DeploymentRequest) -> Result<(), Error> {
+    let results = Policy::load(path)?.evaluate(request)?;
+    let (denied, warnings): (Vec<PolicyResult>, Vec<PolicyResult>) = results.into_iter()
+        .partition(|result| result.action == Action::Deny);
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if denied.is_empty() {
+        Ok(())
+    } else {
+        Err(PolicyDenied { policy: path.to_owned(), results: denied }.into())
+    }
+}
@@ -0,0 +1,83 @@
+use serde_json::{json, Value};
+
+use crate::models::{DeploymentRequest, Kubernetes, Payload};
+use super::{enforce, resolve, Action, Policy, PolicyResult};
+
+fn request(cluster: &str, git_ref: &str, resources: Vec<Value>) -> DeploymentRequest {
+    DeploymentRequest {
+        git_ref: git_ref.to_owned(),
+        auto_merge: false,
+        description: format!("Automated deployment request to {}", cluster),
+        environment: cluster.to_owned(),
+        required_contexts: vec![],
+        payload: Payload { version: vec![1, 0, 0], team: "plattform".to_owned(), kubernetes: Kubernetes { resources } },
+    }
+}
+
+fn application(image: &str, max_replicas: u32) -> Value {
+    json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application", "metadata": {"name": "testapp"},
+        "spec": {"image": image, "replicas": {"min": 1, "max": max_replicas}}})
+}
+
+fn deployment(host_network: bool, image: &str) -> Value {
+    json!({"apiVersion": "apps/v1", "kind": "Deployment", "metadata": {"name": "worker"},
+        "spec": {"template": {"spec": {"hostNetwork": host_network, "containers": [{"name": "worker", "image": image}]}}}})
+}
+
+fn rules(results: Vec<PolicyResult>) -> Vec<(String, Action, String)> {
+    results.into_iter().map(|result| (result.rule, result.action, result.target)).collect()
+}
+
+#[test]
+fn test_compliant_request() {
+    let policy = assert_ok!(Policy::load("testdata/policy.yaml"));
+    let request = request("prod-fss", "v1.0.0", vec![application("navikt/testapp:1.0.0", 4), deployment(false, "navikt/worker:1")]);
+    assert_eq!(Vec::<PolicyResult>::new(), assert_ok!(policy.evaluate(&request)));
+}
+
+#[test]
+fn test_rules_match_clusters_and_kinds() {
+    let policy = assert_ok!(Policy::load("testdata/policy.yaml"));
+    let request = request("dev-fss", "master", vec![application("ghcr.io/other/testapp:1.0.0", 4), deployment(true, "navikt/worker:1")]);
+    assert_eq!(vec![
+        ("no-host-network".to_owned(), Action::Deny, "Deployment/worker".to_owned()),
+        ("max-replicas".to_owned(), Action::Deny, "Application/testapp".to_owned()),
+        ("approved-registries".to_owned(), Action::Deny, "Application/testapp".to_owned()),
+    ], rules(assert_ok!(policy.evaluate(&request))));
+}
+
+#[test]
+fn test_request_target_and_wildcards() {
+    let policy = assert_ok!(Policy::load("testdata/policy.yaml"));
+    let request = request("prod-gcp", "master", vec![deployment(false, "docker.io/library/nginx")]);
+    assert_eq!(vec![
+        ("approved-container-registries".to_owned(), Action::Deny, "Deployment/worker".to_owned()),
+        ("tagged-prod-deploys".to_owned(), Action::Warn, "request".to_owned()),
+    ], rules(assert_ok!(policy.evaluate(&request))));
+}
+
+#[test]
+fn test_resolve_paths() {
+    let value = json!({"spec": {"containers": [{"image": "a"}, {"name": "b"}], "labels": {"x": "1", "y": "2"}}});
+    assert_eq!(vec![Some(&json!("a")), None], resolve(&value, "spec.containers.*.image"));
+    assert_eq!(vec![Some(&json!({"name": "b"}))], resolve(&value, "spec.containers.1"));
+    assert_eq!(vec![Some(&json!("1")), Some(&json!("2"))], resolve(&value, "spec.labels.*"));
+    assert_eq!(vec![None], resolve(&value, "spec.missing.deeper"));
+}
+
+#[test]
+fn test_invalid_rules() {
+    let error = serde_yaml::from_str::<Policy>("rules:\n  - name: a\n    when: []\n    message: b\n    severity: deny\n").unwrap_err();
+    assert!(error.to_string().contains("unknown field `severity`"), "Unexpected error: {}", error);
+
+    let policy: Policy = assert_ok!(serde_yaml::from_str("rules:\n  - name: a\n    when:\n      - path: spec\n        exists: true\n        equals: 1\n    message: b\n"));
+    let error = policy.rules[0].validate().unwrap_err();
+    assert_eq!("The condition on spec needs exactly one of equals, exists, greater_than, less_than, matches and not_matches", error.to_string());
+}
+
+#[test]
+fn test_enforce_denies() {
+    let request = request("dev-fss", "master", vec![deployment(true, "navikt/worker:1")]);
+    let error = enforce("testdata/policy.yaml", &request).unwrap_err();
+    assert_eq!("The deployment is denied by the policy testdata/policy.yaml:\n  [no-host-network] Deployment/worker: Pods can not use the host network", error.to_string());
+}
@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod tests;
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::{Error, ResultExt};
+use glob::Pattern;
+use jrsonnet_evaluator::{EvaluationState, FileImportResolver, Val};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::cli::deploy::template::VariableReference;
+
+const RESOURCE_EXTENSIONS: &[&str] = &["yaml", "yml", "json", "jsonnet"];
+const GLOB_CHARACTERS: &[char] = &['*', '?', '['];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceFormat {
+    Yaml,
+    Json,
+    Jsonnet
+}
+
+impl ResourceFormat {
+    /// Detects the format from the file extension, anything not JSON or Jsonnet is parsed as YAML
+    pub fn from_path(path: &str) -> ResourceFormat {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => ResourceFormat::Json,
+            Some("jsonnet") => ResourceFormat::Jsonnet,
+            _ => ResourceFormat::Yaml,
+        }
+    }
+}
+
+pub fn exclude_patterns<'a, I: IntoIterator<Item=&'a str>>(patterns: I) -> Result<Vec<Pattern>, Error> {
+    patterns.into_iter()
+        .map(|pattern| Pattern::new(pattern)
+            .context(format!("Invalid exclude pattern {}", pattern))
+            .map_err(Error::from))
+        .collect()
+}
+
+/// Expands a resource argument into the files it refers to. Directories are searched recursively for
+/// `*.yaml`, `*.yml`, `*.json` and `*.jsonnet` files and glob patterns are matched, both in sorted order
+pub fn expand_input(argument: &str, excludes: &[Pattern]) -> Result<Vec<String>, Error> {
+    let mut files: Vec<PathBuf> = if argument.contains(GLOB_CHARACTERS) {
+        let mut matches = vec![];
+        for entry in glob::glob(argument).context(format!("Invalid glob pattern {}", argument))? {
+            let path = entry.context(format!("Failed to read a path matching {}", argument))?;
+            if path.is_dir() {
+                walk_dir(&path, &mut matches)?;
+            } else {
+                matches.push(path);
+            }
+        }
+        if matches.is_empty() {
+            return Err(format_err!("No resource files matched {}", argument));
+        }
+        matches
+    } else if Path::new(argument).is_dir() {
+        let mut matches = vec![];
+        walk_dir(Path::new(argument), &mut matches)?;
+        if matches.is_empty() {
+            return Err(format_err!("No resource files found in the directory {}", argument));
+        }
+        matches
+    } else {
+        vec![PathBuf::from(argument)]
+    };
+    files.sort();
+
+    Ok(files.into_iter()
+        .filter(|file| !excludes.iter().any(|exclude| exclude.matches_path(file)))
+        .map(|file| file.display().to_string())
+        .collect())
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries = fs::read_dir(dir)
+        .context(format!("Failed to read resource directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry
+            .context(format!("Failed to read resource directory {}", dir.display()))?
+            .path();
+        if path.is_dir() {
+            walk_dir(&path, files)?;
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| RESOURCE_EXTENSIONS.contains(&e)) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_resources(content: &str, format: ResourceFormat, file_name: &str) -> Result<Vec<Value>, Error> {
+    match format {
+        ResourceFormat::Yaml => parse_yaml_documents(content, file_name),
+        ResourceFormat::Json => parse_json(content, file_name),
+        ResourceFormat::Jsonnet => Err(format_err!("Jsonnet file {} has to be evaluated, not parsed", file_name)),
+    }
+}
+
+/// Parses a JSON resource, a top level array or `kind: List` resource is flattened into its items
+pub fn parse_json(content: &str, file_name: &str) -> Result<Vec<Value>, Error> {
+    let value: Value = serde_json::from_str(content)
+        .context(format!("Failed to parse json in {}", file_name))?;
+    let mut resources = vec![];
+    flatten_into(value, file_name, &mut resources)?;
+    Ok(resources)
+}
+
+/// Evaluates a Jsonnet file, every top level variable of the config is available through `std.extVar` and imports
+/// are resolved relative to the importing file
+pub fn evaluate_jsonnet(file_name: &str, config: Option<&Value>) -> Result<Vec<Value>, Error> {
+    let state = EvaluationState::default();
+    state.with_stdlib();
+    state.set_import_resolver(Box::new(FileImportResolver::default()));
+    if let Some(Value::Object(vars)) = config {
+        for (name, value) in vars {
+            state.add_ext_var(name.as_str().into(), Val::from(value));
+        }
+    }
+
+    let value = state.evaluate_file_raw(Path::new(file_name))
+        .and_then(|value| state.with_tla(value))
+        .and_then(|value| state.run_in_state(|| Value::try_from(&value)))
+        .map_err(|e| format_err!("{}", state.stringify_err(&e)))
+        .context(format!("Failed to evaluate jsonnet file {}", file_name))?;
+
+    let mut resources = vec![];
+    flatten_into(value, file_name, &mut resources)?;
+    Ok(resources)
+}
+
+/// Finds the `std.extVar("name")` calls in a Jsonnet file, which is how it reads the templating variables
+pub fn jsonnet_ext_vars(file_name: &str, content: &str) -> Vec<VariableReference> {
+    let mut references = vec![];
+    for (index, line) in content.lines().enumerate() {
+        for call in line.split("std.extVar(").skip(1) {
+            let argument = call.trim_start();
+            let quote = match argument.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => quote,
+                _ => continue,
+            };
+            if let Some(end) = argument[1..].find(quote) {
+                references.push(VariableReference {
+                    file_name: file_name.to_owned(),
+                    name: argument[1..end + 1].to_owned(),
+                    line: Some(index + 1),
+                });
+            }
+        }
+    }
+    references
+}
+
+/// Parses a YAML stream into resources, one per non-empty document. Top level sequences and `kind: List`
+/// resources are flattened into their items
+pub fn parse_yaml_documents(content: &str, file_name: &str) -> Result<Vec<Value>, Error> {
+    let mut resources = vec![];
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = Value::deserialize(document)
+            .context(format!("Failed to parse yaml in {}", file_name))?;
+        flatten_into(value, file_name, &mut resources)?;
+    }
+    Ok(resources)
+}
+
+/// Writes every resource as a YAML file named `<kind>-<name>.yaml` in the directory, which is created if missing
+pub fn write_resources(out_dir: &str, resources: &[Value]) -> Result<Vec<PathBuf>, Error> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for resource in resources {
+        let kind = resource.get("kind").and_then(Value::as_str)
+            .ok_or_else(|| format_err!("Unable to name a file for a resource without a kind"))?;
+        let name = resource.pointer("/metadata/name").and_then(Value::as_str)
+            .ok_or_else(|| format_err!("Unable to name a file for the {} without a metadata.name", kind))?;
+        let file_name = format!("{}-{}.yaml", kind.to_lowercase(), name).replace(['/', '\\'], "_");
+        let path = Path::new(out_dir).join(file_name);
+        if paths.contains(&path) {
+            return Err(format_err!("Multiple resources would be written to {}, {}/{} is rendered more than once", path.display(), kind, name));
+        }
+        paths.push(path);
+    }
+
+    fs::create_dir_all(out_dir)
+        .context(format!("Failed to create output directory {}", out_dir))?;
+    for (resource, path) in resources.iter().zip(paths.iter()) {
+        let yaml = serde_yaml::to_string(resource)
+            .context(format!("Failed to serialize {} as yaml", path.display()))?;
+        fs::write(path, yaml)
+            .context(format!("Failed to write to output file {}", path.display()))?;
+    }
+    Ok(paths)
+}
+
+fn flatten_into(value: Value, file_name: &str, resources: &mut Vec<Value>) -> Result<(), Error> {
+    match value {
+        Value::Null => {},
+        Value::Array(values) => for value in values {
+            flatten_into(value, file_name, resources)?;
+        },
+        Value::Object(mut object) => {
+            if object.get("kind").and_then(Value::as_str) == Some("List") {
+                match object.remove("items") {
+                    Some(Value::Array(items)) => for item in items {
+                        flatten_into(item, file_name, resources)?;
+                    },
+                    Some(_) => return Err(format_err!("The items of a List in {} have to be a list of resources", file_name)),
+                    None => return Err(format_err!("A List in {} has no items", file_name)),
+                }
+            } else {
+                resources.push(Value::Object(object));
+            }
+        },
+        value => resources.push(value),
+    }
+    Ok(())
+}
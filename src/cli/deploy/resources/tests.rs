@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use super::{evaluate_jsonnet, exclude_patterns, expand_input, jsonnet_ext_vars, parse_json, parse_yaml_documents, write_resources, ResourceFormat};
+
+const RESOURCE_LIST: &str = include_str!("../../../../testdata/resource_list.yaml");
+
+#[test]
+fn test_single_document() {
+    let resources = assert_ok!(parse_yaml_documents("apiVersion: v1\nkind: ServiceAccount\n", "test.yaml"));
+    assert_eq!(vec![json!({"apiVersion": "v1", "kind": "ServiceAccount"})], resources);
+}
+
+#[test]
+fn test_multiple_documents_and_lists() {
+    let resources = assert_ok!(parse_yaml_documents(RESOURCE_LIST, "resource_list.yaml"));
+    let names: Vec<&str> = resources.iter()
+        .map(|r| r["metadata"]["name"].as_str().unwrap())
+        .collect();
+    let kinds: Vec<&str> = resources.iter()
+        .map(|r| r["kind"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(vec!["testapp", "testapp-config", "testapp"], names);
+    assert_eq!(vec!["ServiceAccount", "ConfigMap", "Application"], kinds);
+}
+
+#[test]
+fn test_top_level_sequence() {
+    let resources = assert_ok!(parse_yaml_documents("- kind: A\n- kind: B\n", "test.yaml"));
+    assert_eq!(vec![json!({"kind": "A"}), json!({"kind": "B"})], resources);
+}
+
+#[test]
+fn test_invalid_document() {
+    let error = parse_yaml_documents("kind: A\n---\nkind: [B\n", "test.yaml").unwrap_err();
+    assert_eq!("Failed to parse yaml in test.yaml", error.to_string());
+    let cause = error.iter_causes().next().unwrap().to_string();
+    assert!(cause.contains("line 4"), "Unexpected cause: {}", cause);
+}
+
+#[test]
+fn test_expand_plain_file() {
+    assert_eq!(vec!["testdata/nais.yaml"], assert_ok!(expand_input("testdata/nais.yaml", &[])));
+}
+
+#[test]
+fn test_expand_directory() {
+    assert_eq!(vec!["testdata/manifests/app-test.yaml", "testdata/manifests/app.yaml", "testdata/manifests/extra/serviceaccount.yml"],
+               assert_ok!(expand_input("testdata/manifests", &[])));
+}
+
+#[test]
+fn test_expand_empty_directory() {
+    assert_ok!(std::fs::create_dir_all("target/empty_resources"));
+    let error = expand_input("target/empty_resources", &[]).unwrap_err();
+    assert_eq!("No resource files found in the directory target/empty_resources", error.to_string());
+}
+
+#[test]
+fn test_expand_glob_with_exclude() {
+    let excludes = assert_ok!(exclude_patterns(vec!["*-test.yaml"]));
+    assert_eq!(vec!["testdata/manifests/app.yaml"], assert_ok!(expand_input("testdata/manifests/*.yaml", &excludes)));
+    assert!(expand_input("testdata/manifests/*.json", &[]).is_err(), "A glob without matches should be an error");
+}
+
+#[test]
+fn test_resource_format_from_path() {
+    assert_eq!(ResourceFormat::Yaml, ResourceFormat::from_path("nais.yaml"));
+    assert_eq!(ResourceFormat::Yaml, ResourceFormat::from_path("nais.yml"));
+    assert_eq!(ResourceFormat::Json, ResourceFormat::from_path("nais.json"));
+    assert_eq!(ResourceFormat::Jsonnet, ResourceFormat::from_path("nais.jsonnet"));
+}
+
+#[test]
+fn test_parse_json() {
+    let resources = assert_ok!(parse_json("[{\"kind\": \"A\"}, {\"kind\": \"List\", \"items\": [{\"kind\": \"B\"}]}]", "test.json"));
+    assert_eq!(vec![json!({"kind": "A"}), json!({"kind": "B"})], resources);
+    assert!(parse_json("kind: A", "test.json").is_err());
+}
+
+#[test]
+fn test_list_without_items() {
+    let error = parse_yaml_documents("apiVersion: v1\nkind: List\n", "test.yaml").unwrap_err();
+    assert_eq!("A List in test.yaml has no items", error.to_string());
+    assert!(parse_json("{\"kind\": \"List\", \"items\": {\"kind\": \"A\"}}", "test.json").is_err());
+    assert_eq!(0, assert_ok!(parse_yaml_documents("kind: List\nitems: []\n", "test.yaml")).len());
+}
+
+#[test]
+fn test_evaluate_jsonnet() {
+    let resources = assert_ok!(evaluate_jsonnet("testdata/nais.jsonnet", Some(&json!({"team": "plattform", "version": "1.0.0"}))));
+    assert_eq!(2, resources.len());
+    assert_eq!(json!("testapp-worker"), resources[1]["metadata"]["name"]);
+    assert_eq!(json!("plattform"), resources[1]["metadata"]["labels"]["team"]);
+    assert_eq!(json!("navikt/testapp:1.0.0"), resources[1]["spec"]["image"]);
+}
+
+#[test]
+fn test_evaluate_jsonnet_undefined_external_variable() {
+    let error = evaluate_jsonnet("testdata/nais.jsonnet", None).unwrap_err();
+    assert_eq!("Failed to evaluate jsonnet file testdata/nais.jsonnet", error.to_string());
+}
+
+#[test]
+fn test_jsonnet_ext_vars() {
+    let references = jsonnet_ext_vars("nais.jsonnet", include_str!("../../../../testdata/nais.jsonnet"));
+    let names: Vec<(&str, Option<usize>)> = references.iter().map(|r| (r.name.as_str(), r.line)).collect();
+    assert_eq!(vec![("team", Some(7)), ("version", Some(10))], names);
+}
+
+#[test]
+fn test_write_resources() {
+    let resources = vec![
+        json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application", "metadata": {"name": "testapp"}, "spec": {"port": 8080}}),
+        json!({"apiVersion": "v1", "kind": "ServiceAccount", "metadata": {"name": "testapp"}}),
+    ];
+    let paths = assert_ok!(write_resources("target/render_resources", &resources));
+
+    assert_eq!(vec![PathBuf::from("target/render_resources/application-testapp.yaml"), PathBuf::from("target/render_resources/serviceaccount-testapp.yaml")], paths);
+    let written: serde_json::Value = assert_ok!(serde_yaml::from_str(assert_ok!(std::fs::read_to_string(&paths[0])).as_str()));
+    assert_eq!(resources[0], written);
+}
+
+#[test]
+fn test_write_duplicate_resources() {
+    let resource = json!({"kind": "Application", "metadata": {"name": "testapp"}});
+    let error = write_resources("target/render_duplicates", &[resource.clone(), resource]).unwrap_err();
+    assert_eq!("Multiple resources would be written to target/render_duplicates/application-testapp.yaml, Application/testapp is rendered more than once", error.to_string());
+}
@@ -0,0 +1,253 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+const MAX_LABEL_LENGTH: usize = 63;
+const MAX_SUBDOMAIN_LENGTH: usize = 253;
+/// Kinds whose names are used as DNS labels, other kinds only need a DNS-1123 subdomain
+const DNS_LABEL_KINDS: &[&str] = &["Namespace", "Service"];
+/// RBAC kinds can have path segment names like `system:controller:foo`, which are not checked
+const RBAC_KINDS: &[&str] = &["Role", "ClusterRole", "RoleBinding", "ClusterRoleBinding"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The position of the resource in the payload and its `<kind>/<name>` when known, e.g. `#2 Application/testapp`
+    pub resource: String,
+    pub message: String,
+}
+
+#[derive(Fail, Debug)]
+pub struct InvalidResources {
+    pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for InvalidResources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid resources:")?;
+        for problem in &self.problems {
+            write!(f, "\n  {}: {}", problem.resource, problem.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems that make the resources impossible to apply, and problems that are only reported
+#[derive(Debug, Default)]
+pub struct Report {
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+}
+
+/// Checks that every resource can be applied to a cluster at all: the fields identifying it are present, names and
+/// label and annotation keys are syntactically valid and no two resources identify the same object. Invalid label
+/// values are only warned about, since raw resources can carry placeholders that are replaced later
+pub fn check(resources: &[Value]) -> Report {
+    let mut problems = vec![];
+    let mut warnings = vec![];
+    let mut seen: HashMap<(&str, &str, &str, &str), usize> = HashMap::new();
+
+    for (index, resource) in resources.iter().enumerate() {
+        let position = index + 1;
+        let kind = resource.get("kind").and_then(Value::as_str);
+        let name = resource.pointer("/metadata/name").and_then(Value::as_str);
+        let id = match (kind, name) {
+            (Some(kind), Some(name)) => format!("#{} {}/{}", position, kind, name),
+            (Some(kind), None) => format!("#{} {}", position, kind),
+            _ => format!("#{}", position),
+        };
+        let mut problem = |message: String| problems.push(Problem { resource: id.clone(), message });
+        let mut warning = |message: String| warnings.push(Problem { resource: id.clone(), message });
+
+        let object = match resource.as_object() {
+            Some(object) => object,
+            None => {
+                problem(format!("Expected a mapping, got {}", resource));
+                continue;
+            },
+        };
+        if object.is_empty() {
+            problem("The resource is empty".to_owned());
+            continue;
+        }
+
+        let api_version = required_string(object, "apiVersion", &mut problem);
+        let kind = required_string(object, "kind", &mut problem);
+        let metadata = match object.get("metadata") {
+            Some(Value::Object(metadata)) => metadata,
+            Some(_) => {
+                problem("metadata is not a mapping".to_owned());
+                continue;
+            },
+            None => {
+                problem("metadata.name is missing".to_owned());
+                continue;
+            },
+        };
+
+        let name = match metadata.get("name") {
+            Some(Value::String(name)) => {
+                let kind = kind.unwrap_or("");
+                if DNS_LABEL_KINDS.contains(&kind) {
+                    if let Some(reason) = invalid_dns_label(name) {
+                        problem(format!("metadata.name {:?} is not a valid DNS-1123 label, {}", name, reason));
+                    }
+                } else if !RBAC_KINDS.contains(&kind) {
+                    if let Some(reason) = invalid_dns_subdomain(name) {
+                        problem(format!("metadata.name {:?} is not a valid DNS-1123 subdomain, {}", name, reason));
+                    }
+                }
+                Some(name.as_str())
+            },
+            Some(value) => {
+                problem(format!("metadata.name must be a string, got {}", value));
+                None
+            },
+            None => {
+                problem("metadata.name is missing".to_owned());
+                None
+            },
+        };
+        let namespace = match metadata.get("namespace") {
+            Some(Value::String(namespace)) => {
+                if let Some(reason) = invalid_dns_label(namespace) {
+                    problem(format!("metadata.namespace {:?} is not a valid DNS-1123 label, {}", namespace, reason));
+                }
+                namespace.as_str()
+            },
+            Some(Value::Null) | None => "",
+            Some(value) => {
+                problem(format!("metadata.namespace must be a string, got {}", value));
+                ""
+            },
+        };
+
+        for (field, validate_value) in &[("labels", true), ("annotations", false)] {
+            match metadata.get(*field) {
+                Some(Value::Object(entries)) => for (key, value) in entries {
+                    if let Some(reason) = invalid_qualified_name(key) {
+                        problem(format!("metadata.{} key {:?} is invalid, {}", field, key, reason));
+                    }
+                    match value {
+                        Value::String(value) if *validate_value => if let Some(reason) = invalid_label_value(value) {
+                            warning(format!("metadata.labels.{} value {:?} is invalid, {}", key, value, reason));
+                        },
+                        Value::String(_) => {},
+                        value => problem(format!("metadata.{}.{} must be a string, got {}", field, key, value)),
+                    }
+                },
+                Some(Value::Null) | None => {},
+                Some(_) => problem(format!("metadata.{} is not a mapping", field)),
+            }
+        }
+
+        if let (Some(api_version), Some(kind), Some(name)) = (api_version, kind, name) {
+            if let Some(first) = seen.insert((api_version, kind, namespace, name), position) {
+                let location = if namespace.is_empty() { String::new() } else { format!(" in namespace {}", namespace) };
+                problem(format!("Duplicate of resource #{}, {} {}/{}{} is defined more than once", first, api_version, kind, name, location));
+            }
+        }
+    }
+
+    Report { errors: problems, warnings }
+}
+
+/// Prints the warnings of the checks and fails on any error
+pub fn check_resources(resources: &[Value]) -> Result<(), InvalidResources> {
+    let report = check(resources);
+    for warning in &report.warnings {
+        eprintln!("Warning: {}: {}", warning.resource, warning.message);
+    }
+    if report.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidResources { problems: report.errors })
+    }
+}
+
+fn required_string<'a>(object: &'a Map<String, Value>, field: &str, problem: &mut impl FnMut(String)) -> Option<&'a str> {
+    match object.get(field) {
+        Some(Value::String(value)) if !value.is_empty() => Some(value.as_str()),
+        Some(Value::String(_)) | Some(Value::Null) | None => {
+            problem(format!("{} is missing", field));
+            None
+        },
+        Some(value) => {
+            problem(format!("{} must be a string, got {}", field, value));
+            None
+        },
+    }
+}
+
+/// Why the value is not a DNS-1123 label, at most 63 lowercase alphanumerics and `-` starting and ending with an
+/// alphanumeric
+fn invalid_dns_label(value: &str) -> Option<String> {
+    if value.len() > MAX_LABEL_LENGTH {
+        return Some(format!("it is longer than {} characters", MAX_LABEL_LENGTH));
+    }
+    invalid_dns_segment(value)
+}
+
+/// Why the value is not a DNS-1123 subdomain, at most 253 characters of dot separated DNS-1123 label segments
+fn invalid_dns_subdomain(value: &str) -> Option<String> {
+    if value.len() > MAX_SUBDOMAIN_LENGTH {
+        return Some(format!("it is longer than {} characters", MAX_SUBDOMAIN_LENGTH));
+    }
+    if !value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.') {
+        return Some("it may only contain lowercase alphanumerics, '-' and '.'".to_owned());
+    }
+    if !value.is_empty() && value.split('.').any(str::is_empty) {
+        return Some("it starts or ends with a dot or has two dots in a row".to_owned());
+    }
+    value.split('.').find_map(invalid_dns_segment)
+}
+
+fn invalid_dns_segment(value: &str) -> Option<String> {
+    if value.is_empty() {
+        return Some("it is empty".to_owned());
+    }
+    if !value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        return Some("it may only contain lowercase alphanumerics and '-'".to_owned());
+    }
+    if value.starts_with('-') || value.ends_with('-') {
+        return Some("it must start and end with an alphanumeric".to_owned());
+    }
+    None
+}
+
+/// Why the value is not a label or annotation key, an optional DNS-1123 subdomain prefix and `/` followed by a name
+/// of alphanumerics, `-`, `_` and `.`
+fn invalid_qualified_name(key: &str) -> Option<String> {
+    let name = match key.splitn(2, '/').collect::<Vec<&str>>().as_slice() {
+        [prefix, name] => {
+            if let Some(reason) = invalid_dns_subdomain(prefix) {
+                return Some(format!("the prefix {:?} is not a DNS-1123 subdomain, {}", prefix, reason));
+            }
+            *name
+        },
+        _ => key,
+    };
+    if name.is_empty() {
+        return Some("the name is empty".to_owned());
+    }
+    invalid_label_value(name)
+}
+
+/// Why the value is not a label value, empty or at most 63 alphanumerics, `-`, `_` and `.` starting and ending with
+/// an alphanumeric
+fn invalid_label_value(value: &str) -> Option<String> {
+    if value.len() > MAX_LABEL_LENGTH {
+        return Some(format!("it is longer than {} characters", MAX_LABEL_LENGTH));
+    }
+    if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Some("it may only contain alphanumerics, '-', '_' and '.'".to_owned());
+    }
+    let alphanumeric = |c: Option<char>| c.is_none_or(|c| c.is_ascii_alphanumeric());
+    if !alphanumeric(value.chars().next()) || !alphanumeric(value.chars().last()) {
+        return Some("it must start and end with an alphanumeric".to_owned());
+    }
+    None
+}
@@ -0,0 +1,89 @@
+use serde_json::json;
+
+use super::{check, check_resources, Problem};
+
+fn pairs(problems: Vec<Problem>) -> Vec<(String, String)> {
+    problems.into_iter()
+        .map(|Problem { resource, message }| (resource, message))
+        .collect()
+}
+
+fn problems(resources: &[serde_json::Value]) -> Vec<(String, String)> {
+    pairs(check(resources).errors)
+}
+
+#[test]
+fn test_valid_resources() {
+    let resources = vec![
+        json!({"apiVersion": "v1", "kind": "ServiceAccount", "metadata": {"name": "testapp", "namespace": "default"}}),
+        json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application", "metadata": {"name": "testapp", "namespace": null,
+            "labels": {"team": "plattform", "app.kubernetes.io/name": "testapp", "empty": ""},
+            "annotations": {"nais.io/description": "Anything {goes} here"}}}),
+    ];
+    assert_ok!(check_resources(&resources));
+}
+
+#[test]
+fn test_missing_fields() {
+    let resources = vec![json!({}), json!({"kind": "ConfigMap", "metadata": {}}), json!({"apiVersion": "v1", "metadata": {"name": "testapp"}})];
+    assert_eq!(vec![
+        ("#1".to_owned(), "The resource is empty".to_owned()),
+        ("#2 ConfigMap".to_owned(), "apiVersion is missing".to_owned()),
+        ("#2 ConfigMap".to_owned(), "metadata.name is missing".to_owned()),
+        ("#3".to_owned(), "kind is missing".to_owned()),
+    ], problems(&resources));
+}
+
+#[test]
+fn test_invalid_names() {
+    let resources = vec![
+        json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "Test_App", "namespace": "-default"}}),
+        json!({"apiVersion": "v1", "kind": "Service", "metadata": {"name": "testapp.internal"}}),
+        json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "a".repeat(254)}}),
+        json!({"apiVersion": "v1", "kind": "Secret", "metadata": {"name": "testapp..tls"}}),
+    ];
+    assert_eq!(vec![
+        ("#1 ConfigMap/Test_App".to_owned(), "metadata.name \"Test_App\" is not a valid DNS-1123 subdomain, it may only contain lowercase alphanumerics, '-' and '.'".to_owned()),
+        ("#1 ConfigMap/Test_App".to_owned(), "metadata.namespace \"-default\" is not a valid DNS-1123 label, it must start and end with an alphanumeric".to_owned()),
+        ("#2 Service/testapp.internal".to_owned(), "metadata.name \"testapp.internal\" is not a valid DNS-1123 label, it may only contain lowercase alphanumerics and '-'".to_owned()),
+        (format!("#3 ConfigMap/{}", "a".repeat(254)), format!("metadata.name \"{}\" is not a valid DNS-1123 subdomain, it is longer than 253 characters", "a".repeat(254))),
+        ("#4 Secret/testapp..tls".to_owned(), "metadata.name \"testapp..tls\" is not a valid DNS-1123 subdomain, it starts or ends with a dot or has two dots in a row".to_owned()),
+    ], problems(&resources));
+}
+
+#[test]
+fn test_names_by_kind() {
+    let resources = vec![
+        json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "testapp.config"}}),
+        json!({"apiVersion": "networking.k8s.io/v1", "kind": "Ingress", "metadata": {"name": format!("testapp.{}", "a".repeat(100))}}),
+        json!({"apiVersion": "rbac.authorization.k8s.io/v1", "kind": "ClusterRole", "metadata": {"name": "system:controller:testapp"}}),
+        json!({"apiVersion": "rbac.authorization.k8s.io/v1", "kind": "RoleBinding", "metadata": {"name": "testapp:view"}}),
+        json!({"apiVersion": "v1", "kind": "Namespace", "metadata": {"name": "plattform"}}),
+    ];
+    assert_ok!(check_resources(&resources));
+}
+
+#[test]
+fn test_invalid_labels_and_annotations() {
+    let resources = vec![json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "testapp",
+        "labels": {"team": "{{team}}", "Nais.io/app": "testapp", "replicas": 2},
+        "annotations": {"description/": "testapp", "enabled": true}}})];
+    let report = check(&resources);
+    assert_eq!(vec![
+        ("#1 ConfigMap/testapp".to_owned(), "metadata.labels key \"Nais.io/app\" is invalid, the prefix \"Nais.io\" is not a DNS-1123 subdomain, it may only contain lowercase alphanumerics, '-' and '.'".to_owned()),
+        ("#1 ConfigMap/testapp".to_owned(), "metadata.labels.replicas must be a string, got 2".to_owned()),
+        ("#1 ConfigMap/testapp".to_owned(), "metadata.annotations key \"description/\" is invalid, the name is empty".to_owned()),
+        ("#1 ConfigMap/testapp".to_owned(), "metadata.annotations.enabled must be a string, got true".to_owned()),
+    ], pairs(report.errors));
+    assert_eq!(vec![
+        ("#1 ConfigMap/testapp".to_owned(), "metadata.labels.team value \"{{team}}\" is invalid, it may only contain alphanumerics, '-', '_' and '.'".to_owned()),
+    ], pairs(report.warnings));
+}
+
+#[test]
+fn test_duplicate_resources() {
+    let resource = json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "testapp", "namespace": "default"}});
+    let other_namespace = json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "testapp", "namespace": "plattform"}});
+    let error = check_resources(&[resource.clone(), other_namespace, resource]).unwrap_err();
+    assert_eq!("Invalid resources:\n  #3 ConfigMap/testapp: Duplicate of resource #1, v1 ConfigMap/testapp in namespace default is defined more than once", error.to_string());
+}
@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use failure::{Error, ResultExt};
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+
+use crate::cli::deploy::input;
+use crate::cli::deploy::vars::{self, VarsFormat};
+
+/// The Application CustomResourceDefinition as generated by nais/liberator, replace it to follow the operator
+const APPLICATION_CRD: &str = include_str!("nais.io_applications.yaml");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON pointer to the value that failed validation, empty for the root
+    pub pointer: String,
+    pub message: String,
+}
+
+#[derive(Fail, Debug)]
+pub struct VarsSchemaViolations {
+    pub schema: String,
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for VarsSchemaViolations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Variables do not match the schema {}:", self.schema)?;
+        for violation in &self.violations {
+            let pointer = if violation.pointer.is_empty() { "(root)" } else { violation.pointer.as_str() };
+            write!(f, "\n  {}: {}", pointer, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Fail, Debug)]
+pub struct ResourceViolations {
+    /// The `<kind>/<name>` of the resource together with the violation
+    pub violations: Vec<(String, Violation)>,
+}
+
+impl fmt::Display for ResourceViolations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Resources do not match the schema of their kind (use --validate-resources=false to skip the validation):")?;
+        for (resource, violation) in &self.violations {
+            let pointer = if violation.pointer.is_empty() { "(root)" } else { violation.pointer.as_str() };
+            write!(f, "\n  {} {}: {}", resource, pointer, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a JSON Schema from a JSON or YAML file
+pub fn load_schema(path: &str) -> Result<Value, Error> {
+    let content = input::read_to_string(path)
+        .context(format!("Unable to read schema file {}", path))?;
+    let format = match VarsFormat::from_path(path) {
+        VarsFormat::Yaml => VarsFormat::Yaml,
+        _ => VarsFormat::Json,
+    };
+    vars::parse_vars(content.as_str(), format)
+        .context(format!("Unable to parse schema file {}", path))
+        .map_err(Error::from)
+}
+
+/// Validates the value against the schema, returning every violation ordered by pointer
+pub fn validate(schema: &Value, value: &Value) -> Result<Vec<Violation>, Error> {
+    Ok(violations(&compile(schema)?, value))
+}
+
+fn compile(schema: &Value) -> Result<JSONSchema, Error> {
+    JSONSchema::compile(schema)
+        .map_err(|e| format_err!("Invalid JSON Schema: {} at {}", e, e.schema_path))
+}
+
+fn violations(compiled: &JSONSchema, value: &Value) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = match compiled.validate(value) {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .map(|error| Violation { pointer: error.instance_path.to_string(), message: error.to_string() })
+            .collect(),
+    };
+    violations.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+    violations
+}
+
+/// The schema of the embedded Application CRD, compiled on first use
+fn application_schema() -> &'static JSONSchema {
+    static SCHEMA: OnceLock<JSONSchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let schema = crd_schema(APPLICATION_CRD, "v1alpha1")
+            .expect("The embedded Application CRD has no usable v1alpha1 schema");
+        compile(&schema).expect("The embedded Application CRD is not a valid JSON Schema")
+    })
+}
+
+/// The openAPIV3Schema of one version of a CustomResourceDefinition as a JSON Schema
+fn crd_schema(crd: &str, version: &str) -> Result<Value, Error> {
+    let crd: Value = serde_yaml::from_str(crd)
+        .context("Unable to parse the CustomResourceDefinition")?;
+    let mut schema = crd.pointer("/spec/versions")
+        .and_then(Value::as_array)
+        .and_then(|versions| versions.iter().find(|v| v.get("name").and_then(Value::as_str) == Some(version)))
+        .and_then(|v| v.pointer("/schema/openAPIV3Schema"))
+        .cloned()
+        .ok_or_else(|| format_err!("The CustomResourceDefinition has no openAPIV3Schema for version {}", version))?;
+    structural(&mut schema);
+    Ok(schema)
+}
+
+/// Applies the rules the API server uses for structural schemas that JSON Schema does not have: fields that are not
+/// in the schema are rejected unless it preserves unknown fields, and `nullable` allows null
+fn structural(schema: &mut Value) {
+    let object = match schema {
+        Value::Object(object) => object,
+        _ => return,
+    };
+    if object.remove("nullable") == Some(Value::Bool(true)) {
+        if let Some(kind) = object.get("type").cloned() {
+            object.insert("type".to_owned(), json!([kind, "null"]));
+        }
+    }
+    if object.contains_key("properties") && !object.contains_key("additionalProperties")
+        && object.get("x-kubernetes-preserve-unknown-fields") != Some(&Value::Bool(true)) {
+        object.insert("additionalProperties".to_owned(), Value::Bool(false));
+    }
+
+    if let Some(Value::Object(properties)) = object.get_mut("properties") {
+        properties.values_mut().for_each(structural);
+    }
+    for keyword in &["items", "additionalProperties", "not"] {
+        if let Some(nested) = object.get_mut(*keyword) {
+            structural(nested);
+        }
+    }
+    for keyword in &["allOf", "anyOf", "oneOf"] {
+        if let Some(Value::Array(nested)) = object.get_mut(*keyword) {
+            nested.iter_mut().for_each(structural);
+        }
+    }
+}
+
+/// Validates the merged templating variables against the --vars-schema file
+pub fn validate_vars(schema_path: &str, value: &Value) -> Result<(), Error> {
+    let schema = load_schema(schema_path)?;
+    let violations = validate(&schema, value)
+        .context(format!("Failed to compile the schema {}", schema_path))?;
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(VarsSchemaViolations { schema: schema_path.to_owned(), violations }.into())
+    }
+}
+
+/// Validates every nais.io/v1alpha1 Application against the schema of the embedded Application CRD, other resources
+/// are left to the cluster
+pub fn validate_resources(resources: &[Value]) -> Result<(), Error> {
+    let mut found = vec![];
+    for resource in resources {
+        if resource.get("apiVersion").and_then(Value::as_str) != Some("nais.io/v1alpha1")
+            || resource.get("kind").and_then(Value::as_str) != Some("Application") {
+            continue;
+        }
+        let name = resource.pointer("/metadata/name").and_then(Value::as_str).unwrap_or("<unnamed>");
+        found.extend(violations(application_schema(), resource)
+            .into_iter()
+            .map(|violation| (format!("Application/{}", name), violation)));
+    }
+    if found.is_empty() {
+        Ok(())
+    } else {
+        Err(ResourceViolations { violations: found }.into())
+    }
+}
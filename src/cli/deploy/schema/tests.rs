@@ -0,0 +1,61 @@
+use serde_json::json;
+
+use super::{crd_schema, load_schema, validate, validate_resources, validate_vars, Violation};
+
+#[test]
+fn test_valid_vars() {
+    assert_ok!(validate_vars("testdata/vars.schema.json", &json!({"image": "navikt/testapp:1", "team": "plattform", "replicas": {"min": 2}})));
+}
+
+#[test]
+fn test_every_violation_is_reported() {
+    let schema = assert_ok!(load_schema("testdata/vars.schema.json"));
+    let violations = assert_ok!(validate(&schema, &json!({"team": "plattform", "replicas": {"min": 0, "max": "4"}})));
+
+    assert_eq!(vec![
+        Violation { pointer: "".to_owned(), message: "\"image\" is a required property".to_owned() },
+        Violation { pointer: "/replicas/max".to_owned(), message: "\"4\" is not of type \"integer\"".to_owned() },
+        Violation { pointer: "/replicas/min".to_owned(), message: "0 is less than the minimum of 1".to_owned() },
+    ], violations);
+}
+
+#[test]
+fn test_violations_display() {
+    let error = validate_vars("testdata/vars.schema.json", &json!({"team": "plattform"})).unwrap_err();
+    assert_eq!("Variables do not match the schema testdata/vars.schema.json:\n  (root): \"image\" is a required property", error.to_string());
+}
+
+#[test]
+fn test_validate_applications() {
+    let resources = vec![
+        json!({"apiVersion": "v1", "kind": "ConfigMap", "metadata": {"name": "testapp"}, "spec": {"anything": true}}),
+        json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application", "metadata": {"name": "testapp"}, "spec": {"image": "navikt/testapp:1", "replicas": {"min": 1, "max": 2}}}),
+    ];
+    assert_ok!(validate_resources(&resources));
+}
+
+#[test]
+fn test_application_violations() {
+    let resources = vec![
+        json!({"apiVersion": "nais.io/v1alpha1", "kind": "Application", "metadata": {"name": "testapp"}, "spec": {"port": "8080", "replicas": {"min": 1, "maximum": 4}, "observability": {"autoInstrumentation": {"enabled": true}}}}),
+    ];
+    let error = validate_resources(&resources).unwrap_err();
+    assert_eq!("Resources do not match the schema of their kind (use --validate-resources=false to skip the validation):\n  \
+                Application/testapp /spec: \"image\" is a required property\n  \
+                Application/testapp /spec/port: \"8080\" is not of type \"integer\"\n  \
+                Application/testapp /spec/replicas: Additional properties are not allowed ('maximum' was unexpected)", error.to_string());
+}
+
+#[test]
+fn test_crd_schema() {
+    let crd = "spec:\n  versions:\n  - name: v1alpha1\n    schema:\n      openAPIV3Schema:\n        type: object\n        properties:\n          \
+               spec:\n            type: object\n            properties:\n              ttl:\n                type: string\n                nullable: true\n              \
+               extra:\n                type: object\n                x-kubernetes-preserve-unknown-fields: true\n                properties:\n                  \
+               known:\n                    type: string\n";
+    let schema = assert_ok!(crd_schema(crd, "v1alpha1"));
+
+    assert_eq!(Vec::<Violation>::new(), assert_ok!(validate(&schema, &json!({"spec": {"ttl": null, "extra": {"known": "a", "unknown": 1}}}))));
+    let violations = assert_ok!(validate(&schema, &json!({"spec": {"tll": "1h"}})));
+    assert_eq!(vec![Violation { pointer: "/spec".to_owned(), message: "Additional properties are not allowed ('tll' was unexpected)".to_owned() }], violations);
+    assert!(crd_schema(crd, "v1").is_err());
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use clap::ArgMatches;
+use failure::{Error, ResultExt};
+use glob::Pattern;
+use serde_json::Value;
+
+/// Names of settings that are followed by a credential in strings like `password=hunter2`
+const CREDENTIAL_NAMES: &[&str] = &["password", "passwd", "pwd", "secret", "token", "api_key", "apikey", "access_key", "private_key"];
+const MIN_TOKEN_LENGTH: usize = 24;
+const MIN_HEX_TOKEN_LENGTH: usize = 32;
+/// Bits of entropy per character above which a token looks randomly generated
+const TOKEN_ENTROPY: f64 = 4.5;
+const HEX_TOKEN_ENTROPY: f64 = 3.0;
+/// Length of a hex encoded git commit id, which is often used as image tag or version label
+const COMMIT_ID_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// `<kind>/<name>` for a resource, followed by `:<JSON pointer>` for a value in it, used to allow the finding
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Fail, Debug)]
+pub struct SecretsFound {
+    pub findings: Vec<Finding>,
+}
+
+impl fmt::Display for SecretsFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The payload is stored in the GitHub deployment, readable by anyone with read access to the repository, but it looks like it contains secrets (use --allow-secret=<finding> for each finding that is not a secret):")?;
+        for finding in &self.findings {
+            write!(f, "\n  {}: {}", finding.id, finding.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds Secret resources and strings that look like credentials in the resources, without repeating the values
+pub fn scan_resources(resources: &[Value]) -> Vec<Finding> {
+    let mut findings = vec![];
+    for resource in resources {
+        let kind = resource.get("kind").and_then(Value::as_str).unwrap_or("");
+        let name = resource.pointer("/metadata/name").and_then(Value::as_str).unwrap_or("<unnamed>");
+        let id = format!("{}/{}", kind, name);
+        if kind == "Secret" {
+            findings.push(Finding { id, message: "Secret resources are stored in plain text, keep secrets in Vault or create the Secret in the cluster".to_owned() });
+            continue;
+        }
+        scan_value(resource, &mut String::new(), &mut |pointer, message| {
+            findings.push(Finding { id: format!("{}:{}", id, pointer), message });
+        });
+    }
+    findings
+}
+
+fn scan_value(value: &Value, pointer: &mut String, found: &mut dyn FnMut(&str, String)) {
+    let length = pointer.len();
+    match value {
+        Value::Object(map) => for (key, value) in map {
+            pointer.push('/');
+            pointer.push_str(key.replace('~', "~0").replace('/', "~1").as_str());
+            scan_value(value, pointer, found);
+            pointer.truncate(length);
+        },
+        Value::Array(items) => for (index, value) in items.iter().enumerate() {
+            pointer.push_str(format!("/{}", index).as_str());
+            scan_value(value, pointer, found);
+            pointer.truncate(length);
+        },
+        Value::String(s) => if let Some(message) = scan_string(s) {
+            found(pointer.as_str(), message);
+        },
+        _ => {},
+    }
+}
+
+/// Describes what makes the string look like a credential, if anything
+fn scan_string(s: &str) -> Option<String> {
+    if s.contains("-----BEGIN") && s.contains("PRIVATE KEY-----") {
+        return Some("Contains a PEM encoded private key".to_owned());
+    }
+    if let Some(name) = credential_assignment(s) {
+        return Some(format!("Contains a value for {}", name));
+    }
+    let mut previous = "";
+    for token in s.split(|c: char| !(c.is_ascii_alphanumeric() || "+/=_-".contains(c))) {
+        // Image digests are hashes of the image, not secrets
+        if previous != "sha256" && is_random_token(token) {
+            return Some(format!("Contains a high entropy string of {} characters", token.len()));
+        }
+        previous = token;
+    }
+    None
+}
+
+/// The credential name in a `<name>=<value>` assignment like `DB_PASSWORD=hunter2`, values referencing variables
+/// like `${PASSWORD}` are skipped
+fn credential_assignment(s: &str) -> Option<&str> {
+    for (index, _) in s.match_indices('=') {
+        let name = s[..index].trim_end();
+        let name = &name[name.rfind(|c: char| !(c.is_ascii_alphanumeric() || "_-.".contains(c))).map(|i| i + 1).unwrap_or(0)..];
+        let value_is_literal = s[index + 1..].trim_start().chars().next()
+            .is_some_and(|c| !"$&;".contains(c));
+        if value_is_literal && is_credential_name(name) {
+            return Some(name);
+        }
+    }
+    None
+}
+
+/// Whether the name is one of the credential names, optionally prefixed like `db_password` or `github.token`
+fn is_credential_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    CREDENTIAL_NAMES.iter().any(|credential| match name.strip_suffix(credential) {
+        Some(prefix) => prefix.is_empty() || prefix.ends_with(['_', '-', '.']),
+        None => false,
+    })
+}
+
+/// Long tokens with a high Shannon entropy, hex encoded tokens are held to a lower entropy since they only use 16
+/// characters
+fn is_random_token(token: &str) -> bool {
+    if token.chars().all(|c| c.is_ascii_hexdigit()) {
+        return token.len() >= MIN_HEX_TOKEN_LENGTH && token.len() != COMMIT_ID_LENGTH && entropy(token) >= HEX_TOKEN_ENTROPY;
+    }
+    token.len() >= MIN_TOKEN_LENGTH && entropy(token) >= TOKEN_ENTROPY
+}
+
+fn entropy(token: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in token.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let length = token.chars().count() as f64;
+    counts.values()
+        .map(|&count| count as f64 / length)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+/// Fails when the resources contain secrets that are not allowed with --allow-secret
+pub fn check_resources(subcommand: &ArgMatches, resources: &[Value]) -> Result<(), Error> {
+    let allowed = subcommand.values_of("allow-secret").into_iter()
+        .flatten()
+        .map(|pattern| Pattern::new(pattern).context(format!("Invalid --allow-secret pattern {}", pattern)))
+        .collect::<Result<Vec<Pattern>, _>>()?;
+    let findings: Vec<Finding> = scan_resources(resources).into_iter()
+        .filter(|finding| !allowed.iter().any(|pattern| pattern.matches(finding.id.as_str())))
+        .collect();
+    if findings.is_empty() {
+        Ok(())
+    } else {
+        Err(SecretsFound { findings }.into())
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod tests;
+
+use std::env;
+
+use handlebars::{Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, ScopedJson};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+handlebars_helper!(eq: |a: Json, b: Json| a == b);
+handlebars_helper!(ne: |a: Json, b: Json| a != b);
+
+pub fn register_helpers(reg: &mut Handlebars) {
+    reg.register_helper("default", Box::new(DefaultHelper));
+    reg.register_helper("upper", Box::new(StringHelper(|s| s.to_uppercase())));
+    reg.register_helper("lower", Box::new(StringHelper(|s| s.to_lowercase())));
+    reg.register_helper("base64", Box::new(StringHelper(::base64::encode)));
+    reg.register_helper("json", Box::new(JsonHelper));
+    reg.register_helper("yaml", Box::new(YamlHelper));
+    reg.register_helper("env", Box::new(EnvHelper));
+    reg.register_helper("eq", Box::new(eq));
+    reg.register_helper("ne", Box::new(ne));
+    reg.register_helper("replace", Box::new(ReplaceHelper));
+    reg.register_helper("trunc", Box::new(TruncHelper));
+    reg.register_helper("sha256", Box::new(StringHelper(|s| format!("{:x}", Sha256::digest(s.as_bytes())))));
+}
+
+fn param<'a>(h: &'a Helper, name: &str, idx: usize) -> Result<&'a Value, RenderError> {
+    h.param(idx)
+        .map(|p| p.value())
+        .ok_or_else(|| RenderError::new(format!("`{}` helper: Missing parameter {}", name, idx + 1)))
+}
+
+/// A string parameter, a variable that is not defined fails with the same error as `{{name}}` in strict mode, so it
+/// ends up in the report of undefined variables, and renders as an empty string otherwise
+fn str_param<'a>(h: &'a Helper, r: &Handlebars, idx: usize) -> Result<&'a str, RenderError> {
+    let name = h.name();
+    let parameter = h.param(idx)
+        .ok_or_else(|| RenderError::new(format!("`{}` helper: Missing parameter {}", name, idx + 1)))?;
+    if parameter.is_value_missing() {
+        return if r.strict_mode() {
+            Err(RenderError::strict_error(parameter.path()))
+        } else {
+            Ok("")
+        };
+    }
+    parameter.value()
+        .as_str()
+        .ok_or_else(|| RenderError::new(format!("`{}` helper: Parameter {} has to be a string, got {}", name, idx + 1, parameter.value())))
+}
+
+fn derived<'reg, 'rc>(value: Value) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+    Ok(Some(ScopedJson::Derived(value)))
+}
+
+/// Indents every line except the first, so multi-line output lines up with the placement of the expression
+fn indent_lines(h: &Helper, text: &str) -> String {
+    let indent = h.hash_get("indent")
+        .and_then(|i| i.value().as_u64())
+        .unwrap_or(0) as usize;
+    text.lines()
+        .collect::<Vec<&str>>()
+        .join(format!("\n{}", " ".repeat(indent)).as_str())
+}
+
+/// `{{default value "fallback"}}` renders the fallback when the value is missing, null, false or an empty string
+pub struct DefaultHelper;
+
+impl HelperDef for DefaultHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, _: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let value = param(h, "default", 0)?;
+        let fallback = param(h, "default", 1)?;
+        derived(match value {
+            Value::Null | Value::Bool(false) => fallback.clone(),
+            Value::String(s) if s.is_empty() => fallback.clone(),
+            _ => value.clone(),
+        })
+    }
+}
+
+/// `{{json value}}` serializes a subtree of the vars as compact JSON, use `pretty=true` for indented output
+pub struct JsonHelper;
+
+impl HelperDef for JsonHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, _: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let value = param(h, "json", 0)?;
+        let pretty = h.hash_get("pretty")
+            .and_then(|p| p.value().as_bool())
+            .unwrap_or(false);
+        let json = if pretty {
+            serde_json::to_string_pretty(value)?
+        } else {
+            serde_json::to_string(value)?
+        };
+        derived(Value::String(indent_lines(h, json.as_str())))
+    }
+}
+
+/// `{{yaml value indent=4}}` serializes a subtree of the vars as YAML
+pub struct YamlHelper;
+
+impl HelperDef for YamlHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, _: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let value = param(h, "yaml", 0)?;
+        let yaml = serde_yaml::to_string(value)
+            .map_err(|e| RenderError::new(format!("`yaml` helper: Failed to serialize value: {}", e)))?;
+        let yaml = yaml.trim_start_matches("---\n").trim_end();
+        derived(Value::String(indent_lines(h, yaml)))
+    }
+}
+
+/// `{{env "NAME" "fallback"}}` reads an environment variable, failing in strict mode if it is unset without a fallback
+pub struct EnvHelper;
+
+impl HelperDef for EnvHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, r: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let name = param(h, "env", 0)?
+            .as_str()
+            .ok_or_else(|| RenderError::new("`env` helper: Environment variable name has to be a string"))?;
+        match (env::var(name), h.param(1)) {
+            (Ok(value), _) => derived(Value::String(value)),
+            (Err(_), Some(fallback)) => derived(fallback.value().clone()),
+            (Err(_), None) if r.strict_mode() => Err(RenderError::new(format!("`env` helper: Environment variable {} is not set", name))),
+            (Err(_), None) => derived(Value::Null),
+        }
+    }
+}
+
+/// Helpers transforming a single string, like `{{upper name}}`
+pub struct StringHelper(fn(&str) -> String);
+
+impl HelperDef for StringHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, r: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let s = str_param(h, r, 0)?;
+        derived(Value::String((self.0)(s)))
+    }
+}
+
+/// `{{replace name "-" "_"}}` replaces every occurrence of a substring
+pub struct ReplaceHelper;
+
+impl HelperDef for ReplaceHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, r: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let s = str_param(h, r, 0)?;
+        let from = str_param(h, r, 1)?;
+        let to = str_param(h, r, 2)?;
+        derived(Value::String(s.replace(from, to)))
+    }
+}
+
+/// `{{trunc name 63}}` keeps at most the given number of characters
+pub struct TruncHelper;
+
+impl HelperDef for TruncHelper {
+    fn call_inner<'reg: 'rc, 'rc>(&self, h: &Helper<'reg, 'rc>, r: &'reg Handlebars, _: &'rc Context, _: &mut RenderContext<'reg>) -> Result<Option<ScopedJson<'reg, 'rc>>, RenderError> {
+        let s = str_param(h, r, 0)?;
+        let length = param(h, "trunc", 1)?
+            .as_u64()
+            .ok_or_else(|| RenderError::new("`trunc` helper: Length has to be a positive number"))?;
+        derived(Value::String(s.chars().take(length as usize).collect()))
+    }
+}
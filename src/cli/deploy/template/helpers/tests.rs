@@ -0,0 +1,56 @@
+use serde_json::{json, Value};
+
+use crate::cli::deploy::template::create_registry;
+
+fn render(template: &str, config: &Value) -> String {
+    assert_ok!(create_registry(true).render_template(template, config))
+}
+
+#[test]
+fn test_default() {
+    let config = json!({"present": "value", "empty": ""});
+    assert_eq!("value", render("{{default present \"fallback\"}}", &config));
+    assert_eq!("fallback", render("{{default empty \"fallback\"}}", &config));
+    assert_eq!("fallback", render("{{default missing \"fallback\"}}", &config));
+}
+
+#[test]
+fn test_string_helpers() {
+    let config = json!({"name": "TestApp-with-a-very-long-name"});
+    assert_eq!("TESTAPP-WITH-A-VERY-LONG-NAME", render("{{upper name}}", &config));
+    assert_eq!("testapp", render("{{lower (trunc name 7)}}", &config));
+    assert_eq!("TestApp_with_a_very_long_name", render("{{replace name \"-\" \"_\"}}", &config));
+    assert_eq!("VGVzdEFwcC13aXRoLWEtdmVyeS1sb25nLW5hbWU=", render("{{base64 name}}", &config));
+    assert_eq!("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824", render("{{sha256 \"hello\"}}", &json!({})));
+}
+
+#[test]
+fn test_comparisons() {
+    let config = json!({"cluster": "prod-fss", "replicas": 2});
+    assert_eq!("prod", render("{{#if (eq cluster \"prod-fss\")}}prod{{else}}dev{{/if}}", &config));
+    assert_eq!("dev", render("{{#if (ne cluster \"prod-fss\")}}prod{{else}}dev{{/if}}", &config));
+    assert_eq!("two", render("{{#if (eq replicas 2)}}two{{/if}}", &config));
+}
+
+#[test]
+fn test_serialization_helpers() {
+    let config = json!({"env": {"A": "1", "B": [1, 2]}});
+    assert_eq!("{\"A\":\"1\",\"B\":[1,2]}", render("{{json env}}", &config));
+    assert_eq!("env:\n  A: \"1\"\n  B:\n    - 1\n    - 2", render("env:\n  {{yaml env indent=2}}", &config));
+}
+
+#[test]
+fn test_env() {
+    std::env::set_var("DEPLOYMENT_CLI_HELPER_TEST", "from-env");
+    assert_eq!("from-env", render("{{env \"DEPLOYMENT_CLI_HELPER_TEST\"}}", &json!({})));
+    assert_eq!("fallback", render("{{env \"DEPLOYMENT_CLI_HELPER_TEST_UNSET\" \"fallback\"}}", &json!({})));
+    assert!(create_registry(true).render_template("{{env \"DEPLOYMENT_CLI_HELPER_TEST_UNSET\"}}", &json!({})).is_err());
+    assert_eq!("", assert_ok!(create_registry(false).render_template("{{env \"DEPLOYMENT_CLI_HELPER_TEST_UNSET\"}}", &json!({}))));
+}
+
+#[test]
+fn test_missing_helper_argument() {
+    let error = create_registry(true).render_template("{{lower appname}}", &json!({})).unwrap_err();
+    assert!(error.to_string().contains("Variable \"appname\" not found in strict mode."), "Unexpected error: {}", error);
+    assert_eq!("", assert_ok!(create_registry(false).render_template("{{lower appname}}", &json!({}))));
+}
@@ -0,0 +1,308 @@
+mod helpers;
+mod references;
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use failure::{Error, ResultExt};
+use handlebars::{Handlebars, RenderError, Template, TemplateRenderError};
+use handlebars::template::{Parameter, TemplateElement, TemplateMapping};
+use serde_json::Value;
+
+use crate::cli::deploy::diagnostics::SourceError;
+
+pub use self::references::VariableReference;
+
+const STRICT_ERROR_PREFIX: &str = "Variable ";
+const STRICT_ERROR_SUFFIX: &str = " not found in strict mode.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingVariable {
+    pub file_name: String,
+    pub name: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Fail, Debug)]
+pub struct UndefinedVariables {
+    pub missing: Vec<MissingVariable>
+}
+
+impl fmt::Display for UndefinedVariables {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Templates reference variables that are not defined (use --strict=false to render them as empty strings):")?;
+        for variable in &self.missing {
+            match (variable.line, variable.column) {
+                (Some(line), Some(column)) => writeln!(f, "  {} line {}, col {}: {}", variable.file_name, line, column, variable.name)?,
+                _ => writeln!(f, "  {}: {}", variable.file_name, variable.name)?,
+            }
+        }
+        let names: BTreeSet<&str> = self.missing.iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        write!(f, "Missing keys: {}", names.into_iter().collect::<Vec<&str>>().join(", "))
+    }
+}
+
+pub fn create_registry(strict: bool) -> Handlebars {
+    let mut reg = Handlebars::new();
+    reg.set_strict_mode(strict);
+    helpers::register_helpers(&mut reg);
+    reg
+}
+
+/// Renders a template in strict mode, collecting every undefined variable instead of stopping at the first one.
+/// Returns None if any variables were missing, they are then appended to `missing`.
+pub fn render_strict(reg: &Handlebars, file_name: &str, template: &str, config: &Value, missing: &mut Vec<MissingVariable>) -> Result<Option<String>, Error> {
+    let mut context = config.clone();
+    let mut found: Vec<MissingVariable> = vec![];
+
+    loop {
+        let error = match reg.render_template(template, &context) {
+            Ok(rendered) => return Ok(if found.is_empty() {
+                Some(rendered)
+            } else {
+                missing.extend(found);
+                None
+            }),
+            Err(TemplateRenderError::RenderError(error)) => error,
+            Err(error) => return Err(render_error(file_name, template, error)),
+        };
+
+        let name = match missing_variable_name(&error) {
+            Some(name) => name,
+            None => return Err(render_error(file_name, template, error.into())),
+        };
+
+        let variable = MissingVariable {
+            file_name: file_name.to_owned(),
+            name,
+            line: error.line_no,
+            column: error.column_no,
+        };
+        // A placeholder we could not insert or a variable we have seen before means the next render would fail
+        // at the same spot, so report what we have so far
+        let progressed = !found.contains(&variable) && insert_placeholder(&mut context, &variable.name);
+        found.push(variable);
+        if !progressed {
+            missing.extend(found);
+            return Ok(None);
+        }
+    }
+}
+
+/// Points a render error at the line of the template when handlebars knows it, errors from inside partials keep
+/// the position handlebars reports for the partial
+pub fn render_error(file_name: &str, template: &str, error: TemplateRenderError) -> Error {
+    let located = match &error {
+        TemplateRenderError::TemplateError(e) if e.template_name.is_none() =>
+            e.line_no.map(|line| (line, e.column_no.unwrap_or(1), e.reason.to_string())),
+        TemplateRenderError::RenderError(e) if e.template_name.is_none() =>
+            e.line_no.map(|line| (line, e.column_no.unwrap_or(1), e.desc.clone())),
+        _ => None,
+    };
+    match located {
+        Some((line, column, reason)) => {
+            let message = format!("Failed to render template for file {}: {}", file_name, reason);
+            SourceError::new(file_name, message, template, line, column).into()
+        },
+        None => Error::from(error).context(format!("Failed to render template for file {}", file_name)).into(),
+    }
+}
+
+fn missing_variable_name(error: &RenderError) -> Option<String> {
+    let quoted = error.desc
+        .strip_prefix(STRICT_ERROR_PREFIX)?
+        .strip_suffix(STRICT_ERROR_SUFFIX)?;
+    serde_json::from_str(quoted).ok()
+}
+
+fn insert_placeholder(context: &mut Value, path: &str) -> bool {
+    let mut current = context;
+    for segment in path.split('.') {
+        let segment = segment.trim_start_matches('[').trim_end_matches(']');
+        if segment.is_empty() || segment.starts_with('@') || segment == "this" || segment == ".." {
+            return false;
+        }
+        if current.is_null() {
+            *current = Value::Object(serde_json::Map::new());
+        }
+        current = match current {
+            Value::Object(map) => map.entry(segment.to_owned()).or_insert(Value::Null),
+            Value::Array(values) => match segment.parse::<usize>().ok().and_then(move |i| values.get_mut(i)) {
+                Some(value) => value,
+                None => return false,
+            },
+            _ => return false,
+        };
+    }
+    if !current.is_null() {
+        return false;
+    }
+    *current = Value::String(String::new());
+    true
+}
+
+struct PartialReference {
+    name: String,
+    line: Option<usize>,
+    has_fallback: bool,
+}
+
+/// Registers every file in the directory as a partial named after the file without its extension
+pub fn register_partials(reg: &mut Handlebars, dir: &str) -> Result<(), Error> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).context(format!("Failed to read partials directory {}", dir))? {
+        let path = entry.context(format!("Failed to read partials directory {}", dir))?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut partials = vec![];
+    for path in paths {
+        let name = partial_name_for(&path)?;
+        if reg.get_template(name.as_str()).is_some() {
+            return Err(format_err!("Multiple partials named {} in {}", name, dir));
+        }
+
+        let mut partial = String::new();
+        File::open(&path)
+            .context(format!("Unable to open partial {}", path.display()))?
+            .read_to_string(&mut partial)
+            .context(format!("Failed to read partial {}", path.display()))?;
+        reg.register_partial(name.as_str(), partial.as_str())
+            .context(format!("Failed to parse partial {}", path.display()))?;
+        partials.push((path, partial));
+    }
+
+    // Partials can include each other, so the includes are checked once every partial is registered
+    for (path, partial) in &partials {
+        check_partials(reg, path.display().to_string().as_str(), partial.as_str())?;
+    }
+    check_recursive_partials(reg)
+}
+
+fn partial_name_for(path: &Path) -> Result<String, Error> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem.to_owned())
+        .ok_or_else(|| format_err!("Invalid partial file name {}", path.display()))
+}
+
+/// Every variable the template reads from the root context, including the ones read by partials it includes
+pub fn referenced_variables(reg: &Handlebars, file_name: &str, template: &str) -> Result<Vec<VariableReference>, Error> {
+    let compiled = Template::compile2(template, true)
+        .map_err(|e| render_error(file_name, template, e.into()))?;
+    let mut references = vec![];
+    references::collect_references(reg, file_name, &compiled, &mut references);
+    Ok(references)
+}
+
+/// Verifies that every partial a template includes exists, handlebars silently renders nothing for missing partials
+pub fn check_partials(reg: &Handlebars, file_name: &str, template: &str) -> Result<(), Error> {
+    // Syntax errors are reported when rendering the template
+    let template = match Template::compile2(template, true) {
+        Ok(template) => template,
+        Err(_) => return Ok(()),
+    };
+
+    let mut references = vec![];
+    let mut inline_partials = vec![];
+    collect_partials(&template, &mut references, &mut inline_partials);
+
+    let missing: Vec<String> = references.iter()
+        .filter(|r| !r.has_fallback && reg.get_template(r.name.as_str()).is_none() && !inline_partials.contains(&r.name))
+        .map(|r| match r.line {
+            Some(line) => format!("{} (line {})", r.name, line),
+            None => r.name.clone(),
+        })
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format_err!("Template {} includes partials that do not exist: {}", file_name, missing.join(", ")))
+    }
+}
+
+fn check_recursive_partials(reg: &Handlebars) -> Result<(), Error> {
+    let mut names: Vec<&String> = reg.get_templates().keys().collect();
+    names.sort();
+    for name in names {
+        let mut stack = vec![name.clone()];
+        find_cycle(reg, &mut stack)?;
+    }
+    Ok(())
+}
+
+fn find_cycle(reg: &Handlebars, stack: &mut Vec<String>) -> Result<(), Error> {
+    let template = match reg.get_template(stack.last().unwrap()) {
+        Some(template) => template,
+        None => return Ok(()),
+    };
+
+    let mut references = vec![];
+    collect_partials(template, &mut references, &mut vec![]);
+    for reference in references {
+        if stack.contains(&reference.name) {
+            stack.push(reference.name);
+            return Err(format_err!("Partials include each other recursively: {}", stack.join(" -> ")));
+        }
+        stack.push(reference.name);
+        find_cycle(reg, stack)?;
+        stack.pop();
+    }
+    Ok(())
+}
+
+fn collect_partials(template: &Template, references: &mut Vec<PartialReference>, inline_partials: &mut Vec<String>) {
+    for (index, element) in template.elements.iter().enumerate() {
+        let line = template.mapping.as_ref()
+            .and_then(|mapping| mapping.get(index))
+            .map(|&TemplateMapping(line, _)| line);
+
+        match element {
+            TemplateElement::PartialExpression(directive) | TemplateElement::PartialBlock(directive) => {
+                if let Some(name) = static_name(&directive.name) {
+                    if !name.starts_with('@') {
+                        references.push(PartialReference { name, line, has_fallback: directive.template.is_some() });
+                    }
+                }
+            },
+            TemplateElement::DirectiveBlock(directive) if directive.name.as_name() == Some("inline") => {
+                if let Some(name) = directive.params.first().and_then(static_name) {
+                    inline_partials.push(name);
+                }
+            },
+            _ => {},
+        }
+
+        let nested: Vec<&Template> = match element {
+            TemplateElement::Expression(helper) | TemplateElement::HelperBlock(helper) =>
+                helper.template.iter().chain(helper.inverse.iter()).collect(),
+            TemplateElement::PartialExpression(directive) | TemplateElement::PartialBlock(directive) |
+            TemplateElement::DirectiveExpression(directive) | TemplateElement::DirectiveBlock(directive) =>
+                directive.template.iter().collect(),
+            _ => vec![],
+        };
+        for template in nested {
+            collect_partials(template, references, inline_partials);
+        }
+    }
+}
+
+fn static_name(parameter: &Parameter) -> Option<String> {
+    match parameter {
+        Parameter::Name(name) => Some(name.clone()),
+        Parameter::Literal(Value::String(name)) => Some(name.clone()),
+        _ => None,
+    }
+}
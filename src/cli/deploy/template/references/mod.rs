@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests;
+
+use handlebars::{Handlebars, Template};
+use handlebars::template::{HelperTemplate, Parameter, TemplateElement, TemplateMapping};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableReference {
+    /// The resource file, or `partial <name>` for references inside partials
+    pub file_name: String,
+    pub name: String,
+    pub line: Option<usize>,
+}
+
+/// Walks a compiled template for every variable it reads from the root context. `{{#with}}` blocks are followed,
+/// while names inside `{{#each}}` and other blocks that change the context to an unknown value are skipped, unless
+/// they reach back with `../` or `@root`
+pub fn collect_references(reg: &Handlebars, file_name: &str, template: &Template, references: &mut Vec<VariableReference>) {
+    let mut walker = Walker { reg, references, partials: vec![] };
+    walker.walk(file_name, template, &[Some(String::new())]);
+}
+
+struct Walker<'a> {
+    reg: &'a Handlebars,
+    references: &'a mut Vec<VariableReference>,
+    /// Partials being walked, to avoid following recursive partials forever
+    partials: Vec<String>,
+}
+
+impl<'a> Walker<'a> {
+    /// The scopes are the paths of the contexts of the enclosing blocks, None where the context is unknown
+    fn walk(&mut self, file_name: &str, template: &Template, scopes: &[Option<String>]) {
+        for (index, element) in template.elements.iter().enumerate() {
+            let line = template.mapping.as_ref()
+                .and_then(|mapping| mapping.get(index))
+                .map(|&TemplateMapping(line, _)| line);
+
+            match element {
+                TemplateElement::HTMLExpression(parameter) => self.parameter(file_name, parameter, line, scopes),
+                TemplateElement::Expression(helper) => self.helper(file_name, helper, line, scopes),
+                TemplateElement::HelperBlock(helper) => {
+                    self.helper(file_name, helper, line, scopes);
+                    if let Some(inner) = &helper.template {
+                        let mut inner_scopes = scopes.to_vec();
+                        inner_scopes.push(block_scope(self.reg, helper, scopes));
+                        self.walk(file_name, inner, &inner_scopes);
+                    }
+                    if let Some(inverse) = &helper.inverse {
+                        self.walk(file_name, inverse, scopes);
+                    }
+                },
+                TemplateElement::PartialExpression(directive) | TemplateElement::PartialBlock(directive) => {
+                    for parameter in directive.params.iter().chain(directive.hash.values()) {
+                        self.parameter(file_name, parameter, line, scopes);
+                    }
+                    if let Some(inner) = &directive.template {
+                        self.walk(file_name, inner, scopes);
+                    }
+                    let name = match &directive.name {
+                        Parameter::Name(name) => Some(name.clone()),
+                        Parameter::Literal(serde_json::Value::String(name)) => Some(name.clone()),
+                        _ => None,
+                    };
+                    if let Some(name) = name {
+                        if let Some(partial) = self.reg.get_template(name.as_str()) {
+                            if !self.partials.contains(&name) {
+                                self.partials.push(name.clone());
+                                self.walk(format!("partial {}", name).as_str(), partial, scopes);
+                                self.partials.pop();
+                            }
+                        }
+                    }
+                },
+                TemplateElement::DirectiveExpression(directive) | TemplateElement::DirectiveBlock(directive) => {
+                    for parameter in directive.params.iter().chain(directive.hash.values()) {
+                        self.parameter(file_name, parameter, line, scopes);
+                    }
+                    if let Some(inner) = &directive.template {
+                        self.walk(file_name, inner, scopes);
+                    }
+                },
+                TemplateElement::RawString(_) | TemplateElement::Comment(_) => {},
+            }
+        }
+    }
+
+    fn helper(&mut self, file_name: &str, helper: &HelperTemplate, line: Option<usize>, scopes: &[Option<String>]) {
+        if is_helper_call(self.reg, helper) {
+            for parameter in helper.params.iter().chain(helper.hash.values()) {
+                self.parameter(file_name, parameter, line, scopes);
+            }
+        } else {
+            self.parameter(file_name, &helper.name, line, scopes);
+        }
+    }
+
+    fn parameter(&mut self, file_name: &str, parameter: &Parameter, line: Option<usize>, scopes: &[Option<String>]) {
+        match parameter {
+            Parameter::Name(name) => {
+                if let Some(name) = resolve(name, scopes) {
+                    self.references.push(VariableReference { file_name: file_name.to_owned(), name, line });
+                }
+            },
+            Parameter::Subexpression(subexpression) => match subexpression.element.as_ref() {
+                TemplateElement::Expression(helper) | TemplateElement::HelperBlock(helper) =>
+                    self.helper(file_name, helper, line, scopes),
+                TemplateElement::HTMLExpression(parameter) => self.parameter(file_name, parameter, line, scopes),
+                _ => {},
+            },
+            Parameter::Literal(_) => {},
+        }
+    }
+}
+
+/// `{{name}}` without parameters reads a variable unless a helper with that name exists
+fn is_helper_call(reg: &Handlebars, helper: &HelperTemplate) -> bool {
+    !helper.params.is_empty() || !helper.hash.is_empty() ||
+        helper.name.as_name().is_none_or(|name| reg.get_helper(name).is_some())
+}
+
+/// The context inside a block, `{{#with}}` and sections like `{{#name}}` move into a known path while other
+/// context changing helpers like `{{#each}}` move into values we can not follow
+fn block_scope(reg: &Handlebars, helper: &HelperTemplate, scopes: &[Option<String>]) -> Option<String> {
+    let current = scopes.last().cloned().flatten();
+    if !is_helper_call(reg, helper) {
+        return helper.name.as_name().and_then(|name| resolve(name, scopes));
+    }
+    match helper.name.as_name() {
+        Some("with") => helper.params.first()
+            .and_then(Parameter::as_name)
+            .and_then(|name| resolve(name, scopes)),
+        Some("if") | Some("unless") => current,
+        Some(name) if reg.get_helper(name).is_some() && name != "each" && name != "lookup" => current,
+        _ => None,
+    }
+}
+
+/// Resolves a name used in a template to a dotted path from the root context
+fn resolve(name: &str, scopes: &[Option<String>]) -> Option<String> {
+    if name.starts_with('@') && !name.starts_with("@root") {
+        return None;
+    }
+    let (mut name, mut depth) = match name.strip_prefix("@root") {
+        Some(rest) => (rest.trim_start_matches('.'), 0),
+        None => (name, scopes.len() - 1),
+    };
+    while let Some(rest) = name.strip_prefix("../") {
+        name = rest;
+        depth = depth.checked_sub(1)?;
+    }
+    let name = name.trim_start_matches("./");
+    let name = match name.strip_prefix("this") {
+        Some(rest) if rest.is_empty() || rest.starts_with('.') => rest.trim_start_matches('.'),
+        _ => name,
+    };
+
+    let scope = if depth == 0 { String::new() } else { scopes[depth].clone()? };
+    let path: Vec<String> = scope.split('.')
+        .chain(name.split('.'))
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.trim_start_matches('[').trim_end_matches(']').to_owned())
+        .collect();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.join("."))
+    }
+}
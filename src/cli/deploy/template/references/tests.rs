@@ -0,0 +1,37 @@
+use handlebars::Template;
+
+use crate::cli::deploy::template::create_registry;
+use super::collect_references;
+
+fn names(template: &str) -> Vec<String> {
+    let reg = create_registry(true);
+    let template = Template::compile2(template, true).unwrap();
+    let mut references = vec![];
+    collect_references(&reg, "nais.yaml", &template, &mut references);
+    references.into_iter().map(|r| r.name).collect()
+}
+
+#[test]
+fn test_simple_references() {
+    assert_eq!(vec!["image", "ingress.host", "team"], names("image: {{image}}\nhost: {{ingress.host}}\n{{! comment {{ignored}} }}team: {{{team}}}"));
+}
+
+#[test]
+fn test_helper_parameters_are_references() {
+    assert_eq!(vec!["name", "replicas", "cluster", "host"], names("{{upper name}} {{default replicas 2}} {{#if (eq cluster \"prod-fss\")}}{{host}}{{/if}}"));
+}
+
+#[test]
+fn test_block_scopes() {
+    assert_eq!(vec!["ingress", "ingress.host", "labels", "team", "cluster"],
+               names("{{#with ingress}}{{host}}{{/with}}{{#each labels}}{{@key}}: {{this}} {{../team}} {{@root.cluster}}{{/each}}"));
+}
+
+#[test]
+fn test_references_have_lines() {
+    let reg = create_registry(true);
+    let template = Template::compile2("a: 1\nb: {{image}}\n", true).unwrap();
+    let mut references = vec![];
+    collect_references(&reg, "nais.yaml", &template, &mut references);
+    assert_eq!(Some(2), references[0].line);
+}
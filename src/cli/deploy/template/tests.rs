@@ -0,0 +1,101 @@
+use handlebars::Handlebars;
+use serde_json::json;
+
+use super::{check_partials, create_registry, register_partials, render_strict, MissingVariable, UndefinedVariables};
+
+const TEMPLATE_WITH_TYPOS: &str = include_str!("../../../../testdata/nais_with_typo.yaml");
+
+fn strict_registry() -> Handlebars {
+    create_registry(true)
+}
+
+#[test]
+fn test_render_strict_defined_variables() {
+    let mut missing = vec![];
+    let rendered = render_strict(&strict_registry(), "test.yaml", "image: {{image.name}}:{{version}}", &json!({"image": {"name": "navikt/testapp"}, "version": "1.0.0"}), &mut missing)
+        .unwrap();
+
+    assert_eq!(Some("image: navikt/testapp:1.0.0".to_owned()), rendered);
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn test_render_strict_collects_all_missing_variables() {
+    let mut missing = vec![];
+    let rendered = render_strict(&strict_registry(), "nais_with_typo.yaml", TEMPLATE_WITH_TYPOS, &json!({"team": "plattform", "version": "1.0.0"}), &mut missing)
+        .unwrap();
+
+    assert_eq!(None, rendered);
+    assert_eq!(vec![
+        MissingVariable { file_name: "nais_with_typo.yaml".to_owned(), name: "namespcae".to_owned(), line: Some(5), column: Some(14) },
+        MissingVariable { file_name: "nais_with_typo.yaml".to_owned(), name: "verison".to_owned(), line: Some(9), column: Some(26) },
+    ], missing);
+}
+
+#[test]
+fn test_render_strict_collects_missing_helper_arguments() {
+    let mut missing = vec![];
+    let rendered = render_strict(&strict_registry(), "test.yaml", "name: {{lower appname}}\nimage: {{replace image \"-\" \"_\"}}:{{version}}", &json!({}), &mut missing)
+        .unwrap();
+
+    assert_eq!(None, rendered);
+    let names: Vec<&str> = missing.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(vec!["appname", "image", "version"], names);
+    assert_eq!((Some(1), Some(7)), (missing[0].line, missing[0].column));
+}
+
+#[test]
+fn test_render_strict_ignores_guarded_variables() {
+    let mut missing = vec![];
+    let rendered = render_strict(&strict_registry(), "test.yaml", "{{#if env}}{{env.KEY}}{{/if}}", &json!({}), &mut missing)
+        .unwrap();
+
+    assert_eq!(Some("".to_owned()), rendered);
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn test_undefined_variables_summary() {
+    let error = UndefinedVariables {
+        missing: vec![
+            MissingVariable { file_name: "a.yaml".to_owned(), name: "version".to_owned(), line: Some(2), column: Some(3) },
+            MissingVariable { file_name: "b.yaml".to_owned(), name: "version".to_owned(), line: None, column: None },
+            MissingVariable { file_name: "b.yaml".to_owned(), name: "image".to_owned(), line: None, column: None },
+        ]
+    };
+
+    let message = error.to_string();
+    assert!(message.contains("a.yaml line 2, col 3: version"));
+    assert!(message.ends_with("Missing keys: image, version"));
+}
+
+#[test]
+fn test_partials() {
+    let mut reg = strict_registry();
+    assert_ok!(register_partials(&mut reg, "testdata/partials"));
+
+    let rendered = assert_ok!(reg.render_template("spec:\n{{> probes}}\n{{> resources}}", &json!({"cpu_limit": "200m"})));
+    assert_eq!("spec:\n  liveness:\n    path: /is_alive\n  readiness:\n    path: /is_ready\n\n  resources:\n    limits:\n      cpu: 200m\n", rendered);
+}
+
+#[test]
+fn test_missing_partial() {
+    let mut reg = strict_registry();
+    assert_ok!(register_partials(&mut reg, "testdata/partials"));
+
+    assert_ok!(check_partials(&reg, "test.yaml", "{{> probes}}{{#> missing}}fallback{{/missing}}{{#*inline \"local\"}}x{{/inline}}{{> local}}"));
+    let error = check_partials(&reg, "test.yaml", "spec:\n{{#if probes}}\n{{> probe}}\n{{/if}}").unwrap_err();
+    assert_eq!("Template test.yaml includes partials that do not exist: probe (line 3)", error.to_string());
+}
+
+#[test]
+fn test_missing_nested_partial() {
+    let error = register_partials(&mut strict_registry(), "testdata/partials_missing").unwrap_err();
+    assert_eq!("Template testdata/partials_missing/probes.yaml includes partials that do not exist: readyness (line 3)", error.to_string());
+}
+
+#[test]
+fn test_recursive_partials() {
+    let error = register_partials(&mut strict_registry(), "testdata/partials_recursive").unwrap_err();
+    assert_eq!("Partials include each other recursively: a -> b -> a", error.to_string());
+}
@@ -1,5 +1,6 @@
 use crate::cli::{create_cli_app, execute_command};
 use mockito::{mock, Matcher, Mock};
+use serde_json::json;
 
 const PRIVATE_KEY_B64: &'static str = include_str!("../../../testdata/testkey_windows_newlines.b64");
 const EXPECTED_PAYLOAD: &'static str = include_str!("../../../testdata/expected_payload.json");
@@ -215,3 +216,426 @@ fn test_create_deployment_github_token() {
     deployments_mock.assert();
     status_mock.assert();
 }
+
+#[test]
+fn test_deploy_payload_strict_fails_on_undefined_variables() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais_with_typo.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert!(error.to_string().contains("Missing keys: namespcae, verison"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_deploy_payload_strict_disabled() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--strict", "false", "--resource", "testdata/nais_with_typo.yaml", "--outputfile", "target/payload_lenient.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+}
+
+#[test]
+fn test_create_deployment_with_yaml_vars() {
+    let deployments_mock = deployment_mock(json_matcher(EXPECTED_PAYLOAD_WITH_VARS), basic_auth());
+    let status_mock = status_mock();
+    let args = vec!["deployment-cli", "deploy", "create", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--repository", "navikt/testapp", "--username", "testuser", "--password", "testpassword", "--vars", "testdata/vars.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+    deployments_mock.assert();
+    status_mock.assert();
+}
+
+#[test]
+fn test_create_deployment_with_layered_vars() {
+    let expected_payload = EXPECTED_PAYLOAD_WITH_VARS.replace("test value", "overridden value");
+    let deployments_mock = deployment_mock(json_matcher(expected_payload), basic_auth());
+    let status_mock = status_mock();
+    let args = vec!["deployment-cli", "deploy", "create", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--repository", "navikt/testapp", "--username", "testuser", "--password", "testpassword", "--vars", "testdata/vars.json", "--vars", "testdata/vars_override.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+    deployments_mock.assert();
+    status_mock.assert();
+}
+
+#[test]
+fn test_deploy_payload_explain_vars() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--vars", "testdata/vars.json", "--vars", "testdata/vars_override.json", "--explain-vars", "--outputfile", "target/payload_explain_vars.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+}
+
+#[test]
+fn test_create_deployment_with_vars_dir() {
+    let deployments_mock = deployment_mock(json_matcher(EXPECTED_PAYLOAD_WITH_VARS), basic_auth());
+    let status_mock = status_mock();
+    let args = vec!["deployment-cli", "deploy", "create", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--repository", "navikt/testapp", "--username", "testuser", "--password", "testpassword", "--vars-dir", "testdata/vars_dir", "--require-cluster-vars"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+    deployments_mock.assert();
+    status_mock.assert();
+}
+
+#[test]
+fn test_deploy_payload_missing_required_cluster_vars() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "dev-gcp", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--vars-dir", "testdata/vars_dir", "--require-cluster-vars"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert!(error.to_string().contains("Could not find a vars file for the cluster dev-gcp"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_deploy_payload_missing_vars_dir() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--vars-dir", "testdata/vars_dri"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+
+    let error = super::assemble_vars(result.subcommand_matches("deploy").unwrap(), "master", "prod-fss", "plattform").unwrap_err();
+    assert_eq!("The vars directory testdata/vars_dri does not exist", error.to_string());
+}
+
+#[test]
+fn test_get_resources_multi_document() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/resource_with_directive.yaml", "--raw-resource", "testdata/resource_list.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+    let config = json!({"team": "plattform", "application_name": "testapp"});
+
+    let resources = assert_ok!(super::get_resources(deploy_command, &config));
+    let kinds: Vec<&str> = resources.iter()
+        .map(|r| r["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["ServiceAccount", "Application", "ServiceAccount", "ConfigMap", "Application"], kinds);
+}
+
+#[test]
+fn test_get_resources_from_directory() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--raw-resource", "testdata/manifests", "--exclude", "**/app-test.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let resources = assert_ok!(super::get_resources(deploy_command, &json!({})));
+    let kinds: Vec<&str> = resources.iter()
+        .map(|r| r["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["Application", "ServiceAccount"], kinds);
+}
+
+#[test]
+fn test_get_resources_json_and_jsonnet() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/nais.json", "--resource", "testdata/nais.jsonnet"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+    let config = json!({"team": "plattform", "version": "1.0.0"});
+
+    let resources = assert_ok!(super::get_resources(deploy_command, &config));
+    let images: Vec<&str> = resources.iter()
+        .map(|r| r["spec"]["image"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["navikt/testapp:1.0.0"; 3], images);
+}
+
+#[test]
+fn test_get_resources_with_partials() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/nais_with_partials.yaml", "--partials", "testdata/partials"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let resources = assert_ok!(super::get_resources(deploy_command, &json!({"version": "1.0.0"})));
+    assert_eq!(json!("/is_ready"), resources[0]["spec"]["readiness"]["path"]);
+    assert_eq!(json!("400m"), resources[0]["spec"]["resources"]["limits"]["cpu"]);
+}
+
+#[test]
+fn test_get_resources_with_overlay_and_patch() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--resource", "testdata/nais.yaml", "--overlay-dir", "testdata/overlays", "--patch", "testdata/patch_ingress.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let resources = assert_ok!(super::get_resources(deploy_command, &json!({"team": "plattform", "version": "1.0.0"})));
+    assert_eq!(json!({"min": 2, "max": 8}), resources[0]["spec"]["replicas"]);
+    assert_eq!(json!(["https://testapp.nais.adeo.no"]), resources[0]["spec"]["ingresses"]);
+}
+
+#[test]
+fn test_get_resources_with_missing_overlay_dir() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "dev-gcp", "--team", "plattform", "--resource", "testdata/nais.yaml", "--overlay-dir", "testdata/overlays"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    let resources = assert_ok!(super::get_resources(result.subcommand_matches("deploy").unwrap(), &json!({"team": "plattform", "version": "1.0.0"})));
+    assert_eq!(json!(1), resources[0]["spec"]["replicas"]["min"]);
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--resource", "testdata/nais.yaml", "--overlay-dir", "testdata/overlay"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    let error = super::get_resources(result.subcommand_matches("deploy").unwrap(), &json!({"team": "plattform", "version": "1.0.0"})).unwrap_err();
+    assert_eq!("The overlay directory testdata/overlay does not exist", error.to_string());
+}
+
+#[test]
+fn test_get_resources_with_set_on_raw_resource() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--raw-resource", "testdata/nais_valid_raw.yaml", "--set", "Application/testapp:spec.replicas.min:=2", "--set", "Application/testapp:metadata.namespace=plattform"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let resources = assert_ok!(super::get_resources(deploy_command, &json!({})));
+    assert_eq!(json!(2), resources[0]["spec"]["replicas"]["min"]);
+    assert_eq!(json!("plattform"), resources[0]["metadata"]["namespace"]);
+}
+
+#[test]
+fn test_only_one_argument_reads_stdin() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "-", "--vars", "-"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let error = super::input::check_stdin_arguments(result.subcommand_matches("deploy").unwrap(), super::input::DEPLOY_STDIN_ARGUMENTS).unwrap_err();
+    assert_eq!("Only one of --resource, --raw-resource, --vars and --age-identity can read from stdin, got - for --resource, --vars", error.to_string());
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--vars", "-", "--age-identity", "-"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    let error = super::input::check_stdin_arguments(result.subcommand_matches("deploy").unwrap(), super::input::DEPLOY_STDIN_ARGUMENTS).unwrap_err();
+    assert_eq!("Only one of --resource, --raw-resource, --vars and --age-identity can read from stdin, got - for --vars, --age-identity", error.to_string());
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--raw-resource", "-", "--vars", "testdata/vars.yaml"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    assert_ok!(super::input::check_stdin_arguments(result.subcommand_matches("deploy").unwrap(), super::input::DEPLOY_STDIN_ARGUMENTS));
+}
+
+#[test]
+fn test_vars_command_only_one_argument_reads_stdin() {
+    let args = vec!["deployment-cli", "vars", "decrypt", "--age-identity", "-", "-"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+
+    let error = super::handle_vars_command(result.subcommand_matches("vars").unwrap()).unwrap_err();
+    assert_eq!("Only one of <file> and --age-identity can read from stdin, got - for <file>, --age-identity", error.to_string());
+}
+
+#[test]
+fn test_yaml_error_points_at_template_line() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/configmap_invalid_yaml.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let config = json!({"name": "testapp", "labels": {"a": "1", "b": "2", "c": "3"}, "value": "foo: bar"});
+    let error = super::get_resources(deploy_command, &config).unwrap_err();
+    assert!(error.to_string().contains("--> testdata/configmap_invalid_yaml.yaml:10 (rendered line 14, col 11)"), "{}", error);
+    assert!(error.to_string().contains("10 |   key: {{value}}"), "{}", error);
+}
+
+#[test]
+fn test_referenced_variables() {
+    let args = vec!["deployment-cli", "deploy", "vars", "--team", "plattform", "--resource", "testdata/nais.yaml", "--resource", "testdata/nais.jsonnet"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let references = assert_ok!(super::referenced_variables(deploy_command));
+    let names: Vec<&str> = references.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(vec!["team", "version", "env", "env", "team", "version"], names);
+}
+
+#[test]
+fn test_deploy_payload_fails_vars_schema() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--vars-schema", "testdata/vars.schema.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert_eq!("Variables do not match the schema testdata/vars.schema.json:\n  (root): \"image\" is a required property", error.to_string());
+}
+
+#[test]
+fn test_deploy_payload_with_vars_schema() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--var", "image=navikt/testapp", "--resource", "testdata/nais.yaml", "--vars-schema", "testdata/vars.schema.json", "--outputfile", "target/payload_with_vars_schema.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+}
+
+#[test]
+fn test_env_prefix_precedence() {
+    std::env::set_var("ENV_PREFIX_TEST_HOSTS", "from env");
+    std::env::set_var("ENV_PREFIX_TEST_BUILD__NUMBER", "42");
+    std::env::set_var("ENV_PREFIX_TEST_BUILD__BRANCH", "from env");
+    std::env::set_var("ENV_PREFIX_TEST_TEAM", "from env");
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--vars", "testdata/vars_override.json", "--env-prefix", "ENV_PREFIX_TEST_", "--var", "build.branch=from var"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let vars = assert_ok!(super::assemble_vars(deploy_command, "master", "prod-fss", "plattform"));
+    assert_eq!(json!("from env"), vars.value["hosts"]);
+    assert_eq!(json!({"number": "42", "branch": "from var"}), vars.value["build"]);
+    assert_eq!(json!("plattform"), vars.value["team"]);
+    assert_eq!(Some(&"env ENV_PREFIX_TEST_BUILD__NUMBER".to_owned()), vars.sources.get("build.number"));
+}
+
+#[test]
+fn test_encrypted_vars_file() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--vars", "testdata/vars_encrypted.yaml.age", "--age-identity", "testdata/age_identity.txt"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let vars = assert_ok!(super::assemble_vars(deploy_command, "master", "prod-fss", "plattform"));
+    assert_eq!(json!("encrypted value"), vars.value["env"]["TEST_KEY"]);
+}
+
+#[test]
+fn test_identities_are_only_read_for_encrypted_vars_files() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--vars", "testdata/vars.yaml", "--age-identity", "testdata/vars.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    assert_ok!(super::assemble_vars(deploy_command, "master", "prod-fss", "plattform"));
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--vars", "testdata/vars_encrypted.yaml.age", "--age-identity", "testdata/vars.yaml"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    let error = super::assemble_vars(result.subcommand_matches("deploy").unwrap(), "master", "prod-fss", "plattform").unwrap_err();
+    assert_eq!("Failed to parse age identities from testdata/vars.yaml", error.to_string());
+}
+
+#[test]
+fn test_deploy_render_to_out_dir() {
+    let args = vec!["deployment-cli", "deploy", "render", "--cluster", "prod-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--out-dir", "target/render"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+    let rendered: serde_json::Value = assert_ok!(serde_yaml::from_str(assert_ok!(std::fs::read_to_string("target/render/application-testapp.yaml")).as_str()));
+    assert_eq!(json!("navikt/testapp:1.0.0"), rendered["spec"]["image"]);
+}
+
+#[test]
+fn test_get_resources_validates_applications() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/nais_invalid_application.yaml"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+
+    let error = super::get_resources(result.subcommand_matches("deploy").unwrap(), &json!({})).unwrap_err();
+    assert!(error.to_string().contains("Application/testapp /spec/port: \"8080\" is not of type \"integer\""), "{}", error);
+    assert!(error.to_string().contains("Application/testapp /spec/replicas/max: \"4\" is not of type \"integer\""), "{}", error);
+    assert!(error.to_string().contains("Application/testapp /spec: Additional properties are not allowed ('replica' was unexpected)"), "{}", error);
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/nais_invalid_application.yaml", "--validate-resources=false"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    assert_ok!(super::get_resources(result.subcommand_matches("deploy").unwrap(), &json!({})));
+}
+
+#[test]
+fn test_get_resources_accepts_application_crd_fields() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--team", "plattform", "--resource", "testdata/nais_application_crd_fields.yaml"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+
+    let resources = assert_ok!(super::get_resources(result.subcommand_matches("deploy").unwrap(), &json!({})));
+    assert_eq!(json!(true), resources[0]["spec"]["replicas"]["disableAutoScaling"]);
+}
+
+#[test]
+fn test_deploy_payload_rejects_invalid_resources() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--raw-resource", "testdata/invalid_resources.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert_eq!("Invalid resources:\n  \
+                #1: The resource is empty\n  \
+                #2 ConfigMap/Testapp_Config: metadata.name \"Testapp_Config\" is not a valid DNS-1123 subdomain, it may only contain lowercase alphanumerics, '-' and '.'", error.to_string());
+}
+
+#[test]
+fn test_deploy_payload_rejects_duplicate_resources() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--raw-resource", "testdata/resource_list.yaml", "--raw-resource", "testdata/resource_list.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert!(error.to_string().contains("#4 ServiceAccount/testapp: Duplicate of resource #1"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_deploy_payload_lint_level_error() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "dev-gcp", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--lint-level", "error"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert!(error.to_string().contains("[team-namespace] Application/testapp: metadata.namespace is default"), "Unexpected error: {}", error);
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "dev-gcp", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--lint-level", "error", "--disable-lint", "team-namespace", "--outputfile", "target/lint_payload.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+}
+
+#[test]
+fn test_deploy_payload_denied_by_policy() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "dev-fss", "--team", "plattform", "--var", "version=1.0.0", "--resource", "testdata/nais.yaml", "--policy", "testdata/policy.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert!(error.to_string().contains("[max-replicas] Application/testapp: Applications in dev clusters can run at most 2 replicas"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_deploy_payload_rejects_secrets_unless_allowed() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--raw-resource", "testdata/secret.yaml"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    let error = execute_command(&result).unwrap_err();
+    assert!(error.to_string().contains("\n  Secret/testapp: Secret resources are stored in plain text"), "Unexpected error: {}", error);
+
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--raw-resource", "testdata/secret.yaml", "--allow-secret", "Secret/testapp", "--outputfile", "target/allowed_secret_payload.json"];
+    let matches = create_cli_app().get_matches_from_safe(args);
+
+    let result = assert_ok!(matches);
+
+    assert_ok!(execute_command(&result));
+}
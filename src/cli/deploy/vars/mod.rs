@@ -0,0 +1,399 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use failure::{Error, ResultExt};
+use serde_json::{Map, Value};
+
+use crate::cli::deploy::crypto::{self, LazyIdentities};
+use crate::cli::deploy::input;
+use crate::cli::deploy::template::VariableReference;
+
+pub const VARS_FORMATS: &[&str] = &["json", "yaml", "toml", "env"];
+const VARS_EXTENSIONS: &[&str] = &["json", "yaml", "yml", "toml", "env"];
+const ENCRYPTED_EXTENSION: &str = ".age";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarsFormat {
+    Json,
+    Yaml,
+    Toml,
+    Dotenv
+}
+
+impl VarsFormat {
+    /// Guesses the format from the file extension, falling back to JSON, stdin is read as YAML. An `.age` extension
+    /// for encrypted files is skipped, so `prod-fss.yaml.age` is YAML
+    pub fn from_path(path: &str) -> VarsFormat {
+        let path = Path::new(path.strip_suffix(ENCRYPTED_EXTENSION).unwrap_or(path));
+        if path == Path::new(input::STDIN) {
+            // YAML is a superset of JSON, so it covers both when piping without --vars-format
+            return VarsFormat::Yaml;
+        }
+        if path.file_name().and_then(|n| n.to_str()) == Some(".env") {
+            return VarsFormat::Dotenv;
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => VarsFormat::Yaml,
+            Some("toml") => VarsFormat::Toml,
+            Some("env") => VarsFormat::Dotenv,
+            _ => VarsFormat::Json,
+        }
+    }
+}
+
+impl FromStr for VarsFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(VarsFormat::Json),
+            "yaml" => Ok(VarsFormat::Yaml),
+            "toml" => Ok(VarsFormat::Toml),
+            "env" => Ok(VarsFormat::Dotenv),
+            _ => Err(format_err!("Unknown vars format {}, expected one of {}", s, VARS_FORMATS.join(", "))),
+        }
+    }
+}
+
+/// Templating variables together with where each leaf value came from, keyed by dotted path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vars {
+    pub value: Value,
+    pub sources: BTreeMap<String, String>
+}
+
+impl Vars {
+    pub fn new() -> Vars {
+        Vars { value: Value::Object(Map::new()), sources: BTreeMap::new() }
+    }
+
+    /// Deep merges the overlay on top of the current variables, objects are merged recursively while
+    /// arrays and scalar values are replaced
+    pub fn merge(&mut self, overlay: Value, source: &str) {
+        merge_value(&mut self.value, overlay, "", source, &mut self.sources);
+    }
+
+    pub fn set(&mut self, key: &str, value: Value, source: &str) {
+        let mut object = Map::new();
+        object.insert(key.to_owned(), value);
+        self.merge(Value::Object(object), source);
+    }
+
+    /// Replaces the value at a dotted path like `ingress.host` or `hosts.0`, creating objects and arrays on the way
+    pub fn set_path(&mut self, path: &str, value: Value, source: &str) -> Result<(), Error> {
+        let Vars { value: ref mut current_value, ref mut sources } = *self;
+        let mut current = current_value;
+        let mut current_path = String::new();
+        for segment in path.split('.') {
+            if segment.is_empty() {
+                return Err(format_err!("Invalid variable path {}, empty path segment", path));
+            }
+            let index = segment.parse::<usize>().ok();
+            if current.is_null() && index == Some(0) {
+                *current = Value::Array(vec![]);
+            }
+            current = match current {
+                Value::Array(values) => {
+                    let index = index
+                        .ok_or_else(|| format_err!("Invalid variable path {}, {} is an array and {} is not an index", path, current_path, segment))?;
+                    if index == values.len() {
+                        values.push(Value::Null);
+                    }
+                    values.get_mut(index)
+                        .ok_or_else(|| format_err!("Invalid variable path {}, index {} is out of bounds for {}", path, index, current_path))?
+                }
+                current => {
+                    if !current.is_object() {
+                        sources.remove(&current_path);
+                        *current = Value::Object(Map::new());
+                    }
+                    current.as_object_mut()
+                        .unwrap()
+                        .entry(segment.to_owned())
+                        .or_insert(Value::Null)
+                }
+            };
+            current_path = join_path(current_path.as_str(), segment);
+        }
+        clear_sources(sources, path);
+        *current = value;
+        sources.insert(path.to_owned(), source.to_owned());
+        Ok(())
+    }
+
+    /// The sources of the value at the path, for objects the sources of every value inside it
+    fn sources_of(&self, path: &str) -> Vec<String> {
+        let mut sources: Vec<String> = vec![];
+        for (key, source) in &self.sources {
+            if overlaps(path, key) && !sources.contains(source) {
+                sources.push(source.clone());
+            }
+        }
+        sources
+    }
+}
+
+/// Parses a variable override, `<path>=<value>` sets a string while `<path>:=<json>` sets a typed JSON value
+pub fn parse_override(var: &str) -> Result<(&str, Value), Error> {
+    let equals_index = var.find('=')
+        .ok_or(format_err!("Invalid format for variable override, expected <name>=<value> or <name>:=<json>"))?;
+    let raw_value = &var[equals_index+1..];
+    Ok(if var[..equals_index].ends_with(':') {
+        let value = serde_json::from_str(raw_value)
+            .context(format!("Invalid JSON value for variable override {}", var))?;
+        (&var[..equals_index-1], value)
+    } else {
+        (&var[..equals_index], Value::String(raw_value.to_owned()))
+    })
+}
+
+/// Environment variables starting with the prefix as variable paths, the prefix is stripped, the rest lowercased and
+/// `__` separates nested keys, so `DEPLOY_IMAGE__TAG` becomes `image.tag`. Sorted by name, together with the name
+pub fn env_overrides<I: IntoIterator<Item=(String, String)>>(prefix: &str, env: I) -> Vec<(String, String, String)> {
+    let mut overrides: Vec<(String, String, String)> = env.into_iter()
+        .filter_map(|(name, value)| {
+            let path = name.strip_prefix(prefix)?
+                .to_lowercase()
+                .replace("__", ".");
+            if path.split('.').any(str::is_empty) {
+                eprintln!("Ignoring the environment variable {}, it does not map to a variable name", name);
+                return None;
+            }
+            Some((path, value, name))
+        })
+        .collect();
+    overrides.sort_by(|a, b| a.2.cmp(&b.2));
+    overrides
+}
+
+impl fmt::Display for Vars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, source) in &self.sources {
+            let value = lookup(&self.value, path).cloned().unwrap_or(Value::Null);
+            writeln!(f, "{} = {} ({})", path, value, source)?;
+        }
+        Ok(())
+    }
+}
+
+/// What `deploy vars` shows, the variables referenced by the templates with their values and sources, the ones
+/// that are not defined and the defined ones no template uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarsReport {
+    pub referenced: Vec<ReportedVariable>,
+    pub undefined: Vec<ReportedVariable>,
+    pub unused: Vec<ReportedVariable>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportedVariable {
+    pub name: String,
+    pub value: Value,
+    pub sources: Vec<String>,
+    /// `file:line` of every reference in the templates
+    pub locations: Vec<String>,
+}
+
+impl VarsReport {
+    pub fn new(vars: &Vars, references: &[VariableReference]) -> VarsReport {
+        let mut locations: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for reference in references {
+            let location = match reference.line {
+                Some(line) => format!("{}:{}", reference.file_name, line),
+                None => reference.file_name.clone(),
+            };
+            let entry = locations.entry(reference.name.as_str()).or_default();
+            if !entry.contains(&location) {
+                entry.push(location);
+            }
+        }
+
+        let mut report = VarsReport { referenced: vec![], undefined: vec![], unused: vec![] };
+        for (name, locations) in locations.iter() {
+            let value = lookup(&vars.value, name).cloned().unwrap_or(Value::Null);
+            let variable = ReportedVariable {
+                name: (*name).to_owned(),
+                sources: vars.sources_of(name),
+                value,
+                locations: locations.clone(),
+            };
+            if variable.value.is_null() {
+                report.undefined.push(variable);
+            } else {
+                report.referenced.push(variable);
+            }
+        }
+
+        // Built-in variables are always there, whether the templates use them or not
+        report.unused = vars.sources.iter()
+            .filter(|(_, source)| source.as_str() != "built-in")
+            .filter(|(path, _)| !locations.keys().any(|name| overlaps(name, path)))
+            .map(|(path, source)| ReportedVariable {
+                name: path.clone(),
+                value: lookup(&vars.value, path).cloned().unwrap_or(Value::Null),
+                sources: vec![source.clone()],
+                locations: vec![],
+            })
+            .collect();
+        report
+    }
+}
+
+impl fmt::Display for VarsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Referenced variables:")?;
+        for variable in &self.referenced {
+            writeln!(f, "  {} = {} ({}) used in {}", variable.name, variable.value, variable.sources.join(", "), variable.locations.join(", "))?;
+        }
+        if !self.undefined.is_empty() {
+            writeln!(f, "Undefined variables:")?;
+            for variable in &self.undefined {
+                writeln!(f, "  {} used in {}", variable.name, variable.locations.join(", "))?;
+            }
+        }
+        if !self.unused.is_empty() {
+            writeln!(f, "Unused variables:")?;
+            for variable in &self.unused {
+                writeln!(f, "  {} = {} ({})", variable.name, variable.value, variable.sources.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether one path is the same as, or nested inside, the other
+fn overlaps(a: &str, b: &str) -> bool {
+    a == b || a.starts_with(format!("{}.", b).as_str()) || b.starts_with(format!("{}.", a).as_str())
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn clear_sources(sources: &mut BTreeMap<String, String>, path: &str) {
+    let nested_prefix = format!("{}.", path);
+    let stale: Vec<String> = sources.keys()
+        .filter(|k| path.is_empty() || k.as_str() == path || k.starts_with(nested_prefix.as_str()))
+        .cloned()
+        .collect();
+    for key in stale {
+        sources.remove(&key);
+    }
+}
+
+fn merge_value(base: &mut Value, overlay: Value, path: &str, source: &str, sources: &mut BTreeMap<String, String>) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                let child_path = join_path(path, key.as_str());
+                let child = base_map.entry(key).or_insert(Value::Null);
+                merge_value(child, value, child_path.as_str(), source, sources);
+            }
+        }
+        (base, Value::Object(overlay_map)) => {
+            clear_sources(sources, path);
+            *base = Value::Object(Map::new());
+            merge_value(base, Value::Object(overlay_map), path, source, sources);
+        }
+        (base, overlay) => {
+            clear_sources(sources, path);
+            *base = overlay;
+            sources.insert(path.to_owned(), source.to_owned());
+        }
+    }
+}
+
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |current, key| match current {
+            Value::Object(map) => map.get(key),
+            Value::Array(values) => key.parse::<usize>().ok().and_then(|i| values.get(i)),
+            _ => None,
+        })
+}
+
+/// Finds `<name>.<extension>` in a vars directory for any of the supported extensions, or the encrypted
+/// `<name>.<extension>.age`
+pub fn find_vars_file(dir: &str, name: &str) -> Result<Option<PathBuf>, Error> {
+    if !Path::new(dir).is_dir() {
+        return Err(format_err!("The vars directory {} does not exist", dir));
+    }
+    let candidates: Vec<PathBuf> = VARS_EXTENSIONS.iter()
+        .flat_map(|extension| vec![format!("{}.{}", name, extension), format!("{}.{}{}", name, extension, ENCRYPTED_EXTENSION)])
+        .map(|file_name| Path::new(dir).join(file_name))
+        .filter(|path| path.is_file())
+        .collect();
+
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(candidates.into_iter().next()),
+        _ => Err(format_err!("Found multiple vars files for {} in {}: {}", name, dir, candidates.iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<String>>()
+            .join(", "))),
+    }
+}
+
+/// Loads a vars file, age encrypted files are decrypted in memory with the identities
+pub fn load_vars(path: &str, format: Option<VarsFormat>, identities: &mut LazyIdentities) -> Result<Value, Error> {
+    let mut content = input::read_bytes(path)
+        .context(format!("Unable to read vars file {}", path))?;
+    if crypto::is_encrypted(content.as_slice()) {
+        let identities = identities.get()?.ok_or_else(|| format_err!("Vars file {} is encrypted, pass an age identity file with --age-identity or the identity in {}", path, crypto::IDENTITY_ENV))?;
+        content = crypto::decrypt(content.as_slice(), identities)
+            .context(format!("Unable to decrypt vars file {}", path))?;
+    }
+    let content = String::from_utf8(content)
+        .context(format!("Vars file {} is not valid UTF-8", path))?;
+
+    parse_vars(content.as_str(), format.unwrap_or_else(|| VarsFormat::from_path(path)))
+        .context(format!("Unable to parse vars file {}", path))
+        .map_err(Error::from)
+}
+
+pub fn parse_vars(content: &str, format: VarsFormat) -> Result<Value, Error> {
+    Ok(match format {
+        VarsFormat::Json => serde_json::from_str(content)?,
+        VarsFormat::Yaml => serde_yaml::from_str(content)?,
+        VarsFormat::Toml => toml::from_str(content)?,
+        VarsFormat::Dotenv => parse_dotenv(content)?,
+    })
+}
+
+fn parse_dotenv(content: &str) -> Result<Value, Error> {
+    let mut vars = Map::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_start_matches("export ");
+        let equals_index = line.find('=')
+            .ok_or_else(|| format_err!("Invalid line {}, expected <name>=<value>", index + 1))?;
+        let key = line[0..equals_index].trim();
+        let value = unquote(line[equals_index + 1..].trim());
+        vars.insert(key.to_owned(), Value::String(value));
+    }
+    Ok(Value::Object(vars))
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].to_owned()
+    } else if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1]
+            .replace("\\n", "\n")
+            .replace("\\\"", "\"")
+    } else {
+        value.split(" #").next().unwrap_or("").trim_end().to_owned()
+    }
+}
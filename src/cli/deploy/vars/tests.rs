@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use crate::cli::deploy::crypto::LazyIdentities;
+use crate::cli::deploy::template::VariableReference;
+use super::{env_overrides, find_vars_file, load_vars, parse_override, parse_vars, Vars, VarsFormat, VarsReport};
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(VarsFormat::Json, VarsFormat::from_path("vars.json"));
+    assert_eq!(VarsFormat::Yaml, VarsFormat::from_path("vars/prod-fss.yaml"));
+    assert_eq!(VarsFormat::Yaml, VarsFormat::from_path("vars.yml"));
+    assert_eq!(VarsFormat::Toml, VarsFormat::from_path("vars.toml"));
+    assert_eq!(VarsFormat::Dotenv, VarsFormat::from_path("prod.env"));
+    assert_eq!(VarsFormat::Dotenv, VarsFormat::from_path("config/.env"));
+    assert_eq!(VarsFormat::Json, VarsFormat::from_path("vars"));
+    assert_eq!(VarsFormat::Yaml, VarsFormat::from_path("-"));
+    assert_eq!(VarsFormat::Yaml, VarsFormat::from_path("vars/prod-fss.yaml.age"));
+}
+
+#[test]
+fn test_all_formats_produce_same_value() {
+    let expected = assert_ok!(load_vars("testdata/vars.json", None, &mut LazyIdentities::default()));
+    assert_eq!(expected, assert_ok!(load_vars("testdata/vars.yaml", None, &mut LazyIdentities::default())));
+    assert_eq!(expected, assert_ok!(load_vars("testdata/vars.toml", None, &mut LazyIdentities::default())));
+}
+
+#[test]
+fn test_dotenv() {
+    assert_eq!(json!({"version": "1.0.0", "quoted": "test value"}), assert_ok!(load_vars("testdata/vars.env", None, &mut LazyIdentities::default())));
+    assert_eq!(json!({"a": "b=c", "d": "e"}), assert_ok!(parse_vars("a=b=c\nd=e # comment", VarsFormat::Dotenv)));
+    assert!(parse_vars("not a variable", VarsFormat::Dotenv).is_err());
+}
+
+#[test]
+fn test_explicit_format_overrides_extension() {
+    assert_eq!(json!({"env": {"TEST_KEY": "test value"}}), assert_ok!(load_vars("testdata/vars.json", Some(VarsFormat::Yaml), &mut LazyIdentities::default())));
+    assert!(load_vars("testdata/vars.yaml", Some(VarsFormat::Json), &mut LazyIdentities::default()).is_err());
+}
+
+#[test]
+fn test_merge_objects_recursively_and_replace_arrays() {
+    let mut vars = Vars::new();
+    vars.merge(json!({"image": {"name": "navikt/testapp", "tag": "1.0.0"}, "hosts": ["a", "b"]}), "common.json");
+    vars.merge(json!({"image": {"tag": "2.0.0"}, "hosts": ["c"]}), "prod-fss.json");
+
+    assert_eq!(json!({"image": {"name": "navikt/testapp", "tag": "2.0.0"}, "hosts": ["c"]}), vars.value);
+    assert_eq!(Some(&"common.json".to_owned()), vars.sources.get("image.name"));
+    assert_eq!(Some(&"prod-fss.json".to_owned()), vars.sources.get("image.tag"));
+    assert_eq!(Some(&"prod-fss.json".to_owned()), vars.sources.get("hosts"));
+}
+
+#[test]
+fn test_merge_replaces_sources_of_replaced_subtrees() {
+    let mut vars = Vars::new();
+    vars.merge(json!({"ingress": {"host": "a.nais.io"}}), "common.json");
+    vars.set("ingress", json!("disabled"), "--var");
+    vars.merge(json!({"ingress": {"path": "/"}}), "prod-fss.json");
+
+    assert_eq!(json!({"ingress": {"path": "/"}}), vars.value);
+    assert_eq!(vec!["ingress.path"], vars.sources.keys().collect::<Vec<&String>>());
+}
+
+#[test]
+fn test_explain_vars() {
+    let mut vars = Vars::new();
+    vars.merge(json!({"env": {"TEST_KEY": "test value"}}), "testdata/vars.json");
+    vars.set("team", json!("plattform"), "built-in");
+
+    assert_eq!("env.TEST_KEY = \"test value\" (testdata/vars.json)\nteam = \"plattform\" (built-in)\n", vars.to_string());
+}
+
+#[test]
+fn test_parse_override() {
+    assert_eq!(("name", json!("thisismy=name")), assert_ok!(parse_override("name=thisismy=name")));
+    assert_eq!(("replicas", json!(3)), assert_ok!(parse_override("replicas:=3")));
+    assert_eq!(("enabled", json!(true)), assert_ok!(parse_override("enabled:=true")));
+    assert_eq!(("obj", json!({"a": 1})), assert_ok!(parse_override("obj:={\"a\":1}")));
+    assert!(parse_override("replicas:=three").is_err());
+    assert!(parse_override("replicas").is_err());
+}
+
+#[test]
+fn test_set_nested_path() {
+    let mut vars = Vars::new();
+    vars.merge(json!({"ingress": {"host": "a.nais.io", "path": "/"}, "hosts": ["a", "b"]}), "vars.json");
+
+    assert_ok!(vars.set_path("ingress.host", json!("foo.nais.io"), "--var"));
+    assert_ok!(vars.set_path("hosts.0", json!("c"), "--var"));
+    assert_ok!(vars.set_path("hosts.2", json!("d"), "--var"));
+    assert_ok!(vars.set_path("new.list.0", json!(1), "--var"));
+
+    assert_eq!(json!({"ingress": {"host": "foo.nais.io", "path": "/"}, "hosts": ["c", "b", "d"], "new": {"list": [1]}}), vars.value);
+    assert_eq!(Some(&"--var".to_owned()), vars.sources.get("ingress.host"));
+    assert_eq!(Some(&"vars.json".to_owned()), vars.sources.get("ingress.path"));
+}
+
+#[test]
+fn test_set_invalid_path() {
+    let mut vars = Vars::new();
+    vars.merge(json!({"hosts": ["a"]}), "vars.json");
+
+    assert!(vars.set_path("hosts.5", json!("b"), "--var").is_err());
+    assert!(vars.set_path("hosts.first", json!("b"), "--var").is_err());
+    assert!(vars.set_path("ingress..host", json!("b"), "--var").is_err());
+}
+
+#[test]
+fn test_find_vars_file() {
+    assert_eq!(Some(PathBuf::from("testdata/vars_dir/common.json")), assert_ok!(find_vars_file("testdata/vars_dir", "common")));
+    assert_eq!(Some(PathBuf::from("testdata/vars_dir/prod-fss.yaml")), assert_ok!(find_vars_file("testdata/vars_dir", "prod-fss")));
+    assert_eq!(None, assert_ok!(find_vars_file("testdata/vars_dir", "dev-gcp")));
+    assert!(find_vars_file("testdata", "vars").is_err(), "Multiple files with the same name should be ambiguous");
+    assert_eq!("The vars directory testdata/vars_dri does not exist", find_vars_file("testdata/vars_dri", "common").unwrap_err().to_string());
+}
+
+#[test]
+fn test_vars_report() {
+    let mut vars = Vars::new();
+    vars.merge(json!({"image": "navikt/testapp:1", "ingress": {"host": "testapp.nais.io", "path": "/"}, "unused": 3}), "vars.yaml");
+    vars.set("team", json!("plattform"), "built-in");
+    vars.set("cluster", json!("prod-fss"), "built-in");
+    assert_ok!(vars.set_path("ingress.path", json!("/api"), "--var"));
+
+    let reference = |name: &str, line: usize| VariableReference { file_name: "nais.yaml".to_owned(), name: name.to_owned(), line: Some(line) };
+    let references = vec![reference("team", 3), reference("ingress", 5), reference("version", 7), reference("team", 9)];
+    let report = VarsReport::new(&vars, &references);
+
+    let expected = [
+        "Referenced variables:",
+        "  ingress = {\"host\":\"testapp.nais.io\",\"path\":\"/api\"} (vars.yaml, --var) used in nais.yaml:5",
+        "  team = \"plattform\" (built-in) used in nais.yaml:3, nais.yaml:9",
+        "Undefined variables:",
+        "  version used in nais.yaml:7",
+        "Unused variables:",
+        "  image = \"navikt/testapp:1\" (vars.yaml)",
+        "  unused = 3 (vars.yaml)",
+        "",
+    ];
+    assert_eq!(expected.join("\n"), report.to_string());
+}
+
+#[test]
+fn test_env_overrides() {
+    let env = vec![
+        ("DEPLOY_IMAGE__TAG".to_owned(), "1.2.3".to_owned()),
+        ("PATH".to_owned(), "/usr/bin".to_owned()),
+        ("DEPLOY_BUILD_NUMBER".to_owned(), "42".to_owned()),
+        ("DEPLOY_BROKEN____NAME".to_owned(), "x".to_owned()),
+    ];
+    assert_eq!(vec![
+        ("build_number".to_owned(), "42".to_owned(), "DEPLOY_BUILD_NUMBER".to_owned()),
+        ("image.tag".to_owned(), "1.2.3".to_owned(), "DEPLOY_IMAGE__TAG".to_owned()),
+    ], env_overrides("DEPLOY_", env));
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use clap::ArgMatches;
+use failure::{Error, ResultExt};
+
+use crate::cli::deploy::{diagnostics, input, resources, vars};
+
+const DIFF_CONTEXT: usize = 3;
+
+/// Modification time and size of every watched file, None for files that do not exist
+type Fingerprint = BTreeMap<PathBuf, Option<(SystemTime, u64)>>;
+
+/// Re-renders the payload whenever one of the input files changes, until interrupted. A failing render is reported
+/// and the last good payload is kept
+pub fn watch_payload(subcommand: &ArgMatches, payload_subcmd: &ArgMatches) -> Result<(), Error> {
+    if input::DEPLOY_STDIN_ARGUMENTS.iter()
+        .any(|(name, _)| subcommand.values_of(name).into_iter().flatten().any(|value| value == input::STDIN)) {
+        return Err(format_err!("--watch can not be combined with reading from stdin"));
+    }
+    let auto_merge: bool = subcommand.value_of("auto-merge").unwrap()
+        .parse()
+        .unwrap();
+    let interval: u64 = payload_subcmd.value_of("watch-interval").unwrap()
+        .parse::<u64>()
+        .context("Provided watch-interval could not be parsed as a number")?;
+    let show_diff = payload_subcmd.is_present("diff");
+
+    let mut last_good: Option<String> = None;
+    let mut last_fingerprint: Option<Fingerprint> = None;
+    loop {
+        let files = watched_files(subcommand);
+        let current = fingerprint(&files);
+        if last_fingerprint.as_ref() != Some(&current) {
+            last_fingerprint = Some(current);
+            match render_payload(subcommand, auto_merge) {
+                Ok(payload) => {
+                    write_payload(payload_subcmd, last_good.as_ref(), payload.as_str(), show_diff)?;
+                    last_good = Some(payload);
+                },
+                Err(error) => eprintln!("Failed to render the payload, keeping the last good output: {}", error_chain(&error)),
+            }
+            eprintln!("Watching {} files for changes", files.len());
+        }
+        thread::sleep(Duration::from_millis(interval));
+    }
+}
+
+fn render_payload(subcommand: &ArgMatches, auto_merge: bool) -> Result<String, Error> {
+    let git_ref = subcommand.value_of("ref").unwrap();
+    let cluster = subcommand.value_of("cluster").unwrap();
+    let team = subcommand.value_of("team").unwrap();
+
+    let vars = super::assemble_vars(subcommand, git_ref, cluster, team)?;
+    let deployment_payload = super::build_deployment_request(subcommand, &vars, auto_merge)?;
+    let mut payload = serde_json::to_string_pretty(&deployment_payload)
+        .context("Failed to serialize json")?;
+    payload.push('\n');
+    Ok(payload)
+}
+
+fn write_payload(payload_subcmd: &ArgMatches, last_good: Option<&String>, payload: &str, show_diff: bool) -> Result<(), Error> {
+    if let Some(output_file) = payload_subcmd.value_of("outputfile") {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(output_file)
+            .and_then(|mut file| file.write_all(payload.as_bytes()))
+            .context(format!("Failed to write to output file {}", output_file))?;
+    }
+
+    match last_good {
+        Some(last_good) if show_diff => if last_good == payload {
+            eprintln!("Payload unchanged");
+        } else {
+            print!("{}", diff(last_good, payload));
+        },
+        _ if payload_subcmd.value_of("outputfile").is_none() => print!("{}", payload),
+        _ => eprintln!("Payload written to {}", payload_subcmd.value_of("outputfile").unwrap()),
+    }
+    std::io::stdout().flush()
+        .context("Failed to write to stdout")?;
+    Ok(())
+}
+
+fn error_chain(error: &Error) -> String {
+    error.iter_chain()
+        .map(|cause| cause.to_string())
+        .collect::<Vec<String>>()
+        .join("\nCaused by: ")
+}
+
+/// Every file the payload is rendered from, directories and glob patterns are expanded again on every poll so new
+/// files are picked up. Arguments that fail to expand are watched as they are, the render reports the error
+pub fn watched_files(subcommand: &ArgMatches) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = vec![];
+    let excludes = resources::exclude_patterns(subcommand.values_of("exclude").into_iter().flatten())
+        .unwrap_or_default();
+    for name in &["resource", "raw-resource", "patch"] {
+        for argument in subcommand.values_of(name).into_iter().flatten() {
+            match resources::expand_input(argument, &excludes) {
+                Ok(expanded) => files.extend(expanded.into_iter().map(PathBuf::from)),
+                Err(_) => files.push(PathBuf::from(argument)),
+            }
+        }
+    }
+    for name in &["variables", "vars-schema", "age-identity", "policy"] {
+        files.extend(subcommand.values_of(name).into_iter().flatten().map(PathBuf::from));
+    }
+
+    let cluster = subcommand.value_of("cluster").unwrap();
+    if let Some(vars_dir) = subcommand.value_of("vars-dir") {
+        for name in &["common", cluster] {
+            if let Ok(Some(file)) = vars::find_vars_file(vars_dir, name) {
+                files.push(file);
+            }
+        }
+    }
+    if let Some(partials_dir) = subcommand.value_of("partials") {
+        files.extend(files_in(Path::new(partials_dir)));
+    }
+    if let Some(overlay_dir) = subcommand.value_of("overlay-dir") {
+        files.extend(files_in(&Path::new(overlay_dir).join(cluster)));
+    }
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+        .unwrap_or_default();
+    files.retain(|path| path.is_file());
+    // The directory itself notices files being added or removed
+    files.push(dir.to_path_buf());
+    files
+}
+
+fn fingerprint(files: &[PathBuf]) -> Fingerprint {
+    files.iter()
+        .map(|file| {
+            let metadata = fs::metadata(file).ok()
+                .and_then(|metadata| metadata.modified().ok().map(|modified| (modified, metadata.len())));
+            (file.clone(), metadata)
+        })
+        .collect()
+}
+
+/// A unified diff of the lines, with a few lines of context around every change
+pub fn diff(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    // Every line as (prefix, old line number, new line number, text), line numbers are 0-based
+    let mut lines: Vec<(char, usize, usize, &str)> = vec![];
+    let (mut o, mut n) = (0, 0);
+    let pairs = diagnostics::common_lines(&old_lines, &new_lines);
+    for (new_index, old_index) in pairs.into_iter().chain(std::iter::once((new_lines.len(), old_lines.len()))) {
+        while o < old_index {
+            lines.push(('-', o, n, old_lines[o]));
+            o += 1;
+        }
+        while n < new_index {
+            lines.push(('+', o, n, new_lines[n]));
+            n += 1;
+        }
+        if o < old_lines.len() && n < new_lines.len() {
+            lines.push((' ', o, n, old_lines[o]));
+            o += 1;
+            n += 1;
+        }
+    }
+
+    let changed: Vec<usize> = lines.iter()
+        .enumerate()
+        .filter(|(_, line)| line.0 != ' ')
+        .map(|(index, _)| index)
+        .collect();
+    let mut output = String::new();
+    let mut index = 0;
+    while index < changed.len() {
+        let start = changed[index].saturating_sub(DIFF_CONTEXT);
+        let mut end = changed[index];
+        while index < changed.len() && changed[index] <= end + 2 * DIFF_CONTEXT + 1 {
+            end = changed[index];
+            index += 1;
+        }
+        let end = (end + DIFF_CONTEXT + 1).min(lines.len());
+
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|line| line.0 != '+').count();
+        let new_count = hunk.iter().filter(|line| line.0 != '-').count();
+        output.push_str(format!("@@ -{},{} +{},{} @@\n", hunk[0].1 + 1, old_count, hunk[0].2 + 1, new_count).as_str());
+        for (prefix, _, _, text) in hunk {
+            output.push_str(format!("{}{}\n", prefix, text).as_str());
+        }
+    }
+    output
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use crate::cli::create_cli_app;
+use super::{diff, watch_payload, watched_files};
+
+#[test]
+fn test_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+    let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\nk\nl\nm\nn\no\n";
+
+    assert_eq!("@@ -2,7 +2,7 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n@@ -12,3 +12,4 @@\n l\n m\n n\n+o\n", diff(old, new));
+}
+
+#[test]
+fn test_diff_of_equal_lines() {
+    assert_eq!("", diff("a\nb\n", "a\nb\n"));
+}
+
+#[test]
+fn test_watched_files() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--watch", "--team", "plattform", "--cluster", "prod-fss",
+                    "--resource", "testdata/manifests", "--exclude", "**/app-test.yaml", "--vars", "testdata/vars.yaml",
+                    "--vars-dir", "testdata/vars_dir", "--partials", "testdata/partials"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+
+    let files = watched_files(result.subcommand_matches("deploy").unwrap());
+    assert_eq!(vec![
+        PathBuf::from("testdata/manifests/app.yaml"),
+        PathBuf::from("testdata/manifests/extra/serviceaccount.yml"),
+        PathBuf::from("testdata/partials"),
+        PathBuf::from("testdata/partials/probes.yaml"),
+        PathBuf::from("testdata/partials/resources.yaml"),
+        PathBuf::from("testdata/vars.yaml"),
+        PathBuf::from("testdata/vars_dir/common.json"),
+        PathBuf::from("testdata/vars_dir/prod-fss.yaml"),
+    ], files);
+}
+
+#[test]
+fn test_watched_policy_file() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--watch", "--team", "plattform", "--cluster", "prod-fss",
+                    "--resource", "testdata/nais.yaml", "--policy", "testdata/policy.yaml"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+
+    let files = watched_files(result.subcommand_matches("deploy").unwrap());
+    assert_eq!(vec![PathBuf::from("testdata/nais.yaml"), PathBuf::from("testdata/policy.yaml")], files);
+}
+
+#[test]
+fn test_watch_rejects_stdin() {
+    let args = vec!["deployment-cli", "deploy", "payload", "--cluster", "prod-fss", "--team", "plattform", "--resource", "testdata/nais.yaml",
+                    "--vars", "testdata/vars_encrypted.yaml.age", "--age-identity", "-", "--watch"];
+    let result = assert_ok!(create_cli_app().get_matches_from_safe(args));
+    let deploy_command = result.subcommand_matches("deploy").unwrap();
+
+    let error = watch_payload(deploy_command, deploy_command.subcommand_matches("payload").unwrap()).unwrap_err();
+    assert_eq!("--watch can not be combined with reading from stdin", error.to_string());
+}
@@ -2,7 +2,7 @@ mod deploy;
 pub mod token;
 mod exchange_token;
 
-use clap::{App, Arg, SubCommand, ArgMatches};
+use clap::{App, AppSettings, Arg, SubCommand, ArgMatches};
 use failure::Error;
 
 const ALLOWED_CLUSTERS: &[&'static str] = &["dev-fss", "dev-sbs", "prod-fss", "prod-sbs", "staging-gcp", "dev-gcp", "prod-gcp", "kubeflow"];
@@ -20,6 +20,10 @@ pub fn execute_command(args: &ArgMatches) -> Result<(), Error> {
         return deploy::handle_deploy_command(deploy_command);
     }
 
+    if let Some(vars_command) = args.subcommand_matches("vars") {
+        return deploy::handle_vars_command(vars_command);
+    }
+
     if let Some(exchange_token_command) = args.subcommand_matches("exchange_token") {
         return exchange_token::exchange_token_command(exchange_token_command);
     }
@@ -71,22 +75,121 @@ pub fn create_cli_app<'a, 'b>() -> App<'a, 'b> {
         .arg(Arg::with_name("resource")
             .short("r")
             .long("resource")
-            .help("Kubernetes resource to apply (current only supports YAML files)")
+            .help("Kubernetes resource to apply, either a file, a directory or a glob pattern. Supports YAML files, which may contain multiple documents, JSON files and Jsonnet files evaluated with the variables as external variables. Use - to read YAML or JSON from stdin")
             .multiple(true)
             .global(true)
             .takes_value(true))
         .arg(Arg::with_name("raw-resource")
             .long("raw-resource")
-            .help("Kubernetes resource to apply without any templating (currently only supports YAML files)")
+            .help("Kubernetes resource to apply without any templating, either a file, a directory or a glob pattern. Supports YAML, JSON and Jsonnet files. Use - to read YAML or JSON from stdin")
+            .multiple(true)
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("validate-resources")
+            .long("validate-resources")
+            .help("Validate nais.io/v1alpha1 Application resources against the embedded Application schema before deploying")
+            .takes_value(true)
+            .default_value("true")
+            .possible_values(&["true", "false"])
+            .global(true))
+        .arg(Arg::with_name("lint-level")
+            .long("lint-level")
+            .help("How to treat resources that break the lint rules for the team and cluster: ignore them, print warnings or fail")
+            .takes_value(true)
+            .default_value("warn")
+            .possible_values(deploy::LINT_LEVELS)
+            .global(true))
+        .arg(Arg::with_name("disable-lint")
+            .long("disable-lint")
+            .help("Lint rule to skip, can be repeated")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .possible_values(deploy::LINT_RULES)
+            .global(true))
+        .arg(Arg::with_name("policy")
+            .long("policy")
+            .help("YAML policy file with rules the deployment request is checked against, a deny rule fails the deployment")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::with_name("allow-secret")
+            .long("allow-secret")
+            .help("Finding of the secret scan to allow, as <kind>/<name> or <kind>/<name>:<JSON pointer> from the error, glob patterns are supported. Can be repeated")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .global(true))
+        .arg(Arg::with_name("partials")
+            .long("partials")
+            .help("Directory of handlebars partials, every file is available in resource templates by its name without extension, e.g. {{> probes}}")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::with_name("patch")
+            .long("patch")
+            .help("File with merge patches or JSON patches to apply to the resources after templating, matched by apiVersion, kind and metadata.name")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("set")
+            .long("set")
+            .help("Overrides an existing field in a rendered resource, <kind>/<name>:<path>=<value> for strings or <kind>/<name>:<path>:=<json> for typed values, e.g. Application/testapp:spec.replicas.min:=2")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("overlay-dir")
+            .long("overlay-dir")
+            .help("Directory containing a sub directory of patch files for each cluster, the patches for --cluster are applied before any --patch files")
+            .global(true)
+            .takes_value(true))
+        .arg(Arg::with_name("exclude")
+            .long("exclude")
+            .help("Glob pattern for resource files to skip when expanding --resource and --raw-resource directories and patterns")
             .multiple(true)
+            .number_of_values(1)
             .global(true)
             .takes_value(true))
         .arg(Arg::with_name("variables")
             .short("v")
             .long("vars")
-            .help("Path to json file containing additional variables to use when templating")
+            .help("Path to file containing additional variables to use when templating (JSON, YAML, TOML or .env), can be repeated to deep merge several files. Use - to read from stdin")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .global(true))
+        .arg(Arg::with_name("vars-dir")
+            .long("vars-dir")
+            .help("Directory containing common.<ext> and <cluster>.<ext> variables files, loaded before any --vars files")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::with_name("require-cluster-vars")
+            .long("require-cluster-vars")
+            .help("Fail if --vars-dir does not contain a variables file for the cluster")
+            .requires("vars-dir")
+            .global(true))
+        .arg(Arg::with_name("env-prefix")
+            .long("env-prefix")
+            .help("Copies environment variables starting with the prefix into the variables, e.g. DEPLOY_ makes DEPLOY_BUILD__NUMBER available as {{build.number}}. Overrides --vars files, but not the built-in variables or --var")
+            .takes_value(true)
+            .global(true))
+        .arg(Arg::with_name("age-identity")
+            .long("age-identity")
+            .help("File with age identities (AGE-SECRET-KEY-1...) to decrypt age encrypted --vars files, the identities can also be passed in the DEPLOYMENT_AGE_IDENTITY environment variable")
+            .takes_value(true)
+            .env("DEPLOYMENT_AGE_IDENTITY_FILE")
+            .global(true))
+        .arg(Arg::with_name("vars-schema")
+            .long("vars-schema")
+            .help("JSON Schema (JSON or YAML) the merged variables are validated against before any template is rendered")
             .takes_value(true)
             .global(true))
+        .arg(Arg::with_name("vars-format")
+            .long("vars-format")
+            .help("Format of the variables file, if omitted it is detected from the file extension")
+            .takes_value(true)
+            .possible_values(deploy::VARS_FORMATS)
+            .global(true))
         .arg(Arg::with_name("ref")
             .short("g")
             .long("ref")
@@ -115,10 +218,17 @@ pub fn create_cli_app<'a, 'b>() -> App<'a, 'b> {
             .global(true))
         .arg(Arg::with_name("var")
             .long("var")
-            .help("Overrides templating variable")
+            .help("Overrides templating variable, <path>=<value> for strings or <path>:=<json> for typed values. Paths can be nested, e.g. ingress.host or hosts.0")
             .multiple(true)
             .takes_value(true)
             .global(true))
+        .arg(Arg::with_name("strict")
+            .long("strict")
+            .help("Fail when a template references a variable that is not defined instead of rendering an empty string")
+            .takes_value(true)
+            .default_value("true")
+            .possible_values(&["true", "false"])
+            .global(true))
         .arg(Arg::with_name("auto-merge")
             .long("auto-merge")
             .help("Should github try to automatically merge the default branch into ref")
@@ -165,6 +275,36 @@ pub fn create_cli_app<'a, 'b>() -> App<'a, 'b> {
         .subcommand(with_credentials_args(SubCommand::with_name("jwt")
             .about("Generate a app installation jwt")))
 
+        .subcommand(SubCommand::with_name("vars")
+            .about("Encrypt and decrypt variables files with age")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("encrypt")
+                .about("Encrypts a variables file, ASCII armored, to the recipients or to the public keys of the age identity")
+                .arg(Arg::with_name("recipient")
+                    .long("recipient")
+                    .help("Public key to encrypt to (age1...), can be repeated")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .takes_value(true))
+                .arg(Arg::with_name("outputfile")
+                    .short("o")
+                    .long("outputfile")
+                    .help("File to output to, if omitted it will print to stdout")
+                    .takes_value(true))
+                .arg(Arg::with_name("file")
+                    .help("Variables file to encrypt, - for stdin")
+                    .required(true)))
+            .subcommand(SubCommand::with_name("decrypt")
+                .about("Decrypts an age encrypted variables file with the age identity")
+                .arg(Arg::with_name("outputfile")
+                    .short("o")
+                    .long("outputfile")
+                    .help("File to output to, if omitted it will print to stdout")
+                    .takes_value(true))
+                .arg(Arg::with_name("file")
+                    .help("Encrypted variables file to decrypt, - for stdin")
+                    .required(true))))
+
         .subcommand(SubCommand::with_name("deploy")
             .about("Command for github deployments")
 
@@ -200,11 +340,36 @@ pub fn create_cli_app<'a, 'b>() -> App<'a, 'b> {
                     .default_value("5000")
                     .required(true))))
 
+            .subcommand(SubCommand::with_name("vars")
+                .about("Lists the variables referenced by the --resource templates, their values and where they came from, and flags undefined and unused variables"))
+
+            .subcommand(SubCommand::with_name("render")
+                .about("Renders the resources and writes each of them as a YAML file named <kind>-<name>.yaml, to inspect them or use with kubectl diff")
+                .arg(Arg::with_name("out-dir")
+                    .long("out-dir")
+                    .help("Directory to write the resources to, created if it does not exist")
+                    .takes_value(true)
+                    .required(true)))
+
             .subcommand(SubCommand::with_name("payload")
                 .about("Templates the deployment payload for the github deployment api, useful for manual curl calls/debugging")
                 .arg(Arg::with_name("outputfile")
                     .short("o")
                     .long("outputfile")
                     .help("File to output to, if omitted it will print to stdout")
-                    .takes_value(true))))
+                    .takes_value(true))
+                .arg(Arg::with_name("explain-vars")
+                    .long("explain-vars")
+                    .help("Print every templating variable and where its value came from to stderr"))
+                .arg(Arg::with_name("watch")
+                    .long("watch")
+                    .help("Keep running and render the payload, as indented JSON, again whenever a resource, vars, partial or patch file changes. A failing render keeps the last good payload"))
+                .arg(Arg::with_name("diff")
+                    .long("diff")
+                    .requires("watch")
+                    .help("With --watch, print a diff against the previous payload instead of the full payload"))
+                .arg(Arg::with_name("watch-interval")
+                    .long("watch-interval")
+                    .help("Specifies the interval in ms used for polling files for changes with --watch")
+                    .default_value("500"))))
 }
@@ -1,9 +1,15 @@
+#[macro_use]
 extern crate handlebars;
 
+extern crate age;
 extern crate base64;
 extern crate clap;
 #[macro_use]
 extern crate failure;
+extern crate glob;
+extern crate jrsonnet_evaluator;
+extern crate json_patch;
+extern crate jsonschema;
 extern crate jsonwebtoken as jwt;
 #[cfg(test)]
 extern crate mockito;
@@ -14,6 +20,8 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_yaml;
 extern crate rpassword;
+extern crate sha2;
+extern crate toml;
 
 #[cfg(test)]
 #[macro_use]